@@ -2,7 +2,19 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-const GITIGNORE_SUFFIX: &str = ".gitignore";
+#[path = "src/layout.rs"]
+mod layout;
+
+use layout::{collect_templates, compute_dest_name, upstream_path};
+
+/// Records the upstream revision of vendored templates that have no `.git`:
+/// one line of `<commit> <commit date>`, as printed by
+/// `git log -1 --format='%H %cI'`.
+const REVISION_FILE: &str = ".gig-revision";
+
+/// Maps each embedded template to the path it came from upstream: one
+/// `<dest name>\t<upstream path>` line per template, in `OUT_DIR`.
+const UPSTREAM_MANIFEST: &str = "upstream.tsv";
 
 fn main() {
     let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
@@ -25,6 +37,12 @@ fn main() {
             panic!("Failed to clone gitignore templates repository");
         }
 
+        // Keep the revision before the history goes away
+        if let Some(revision) = git_revision(src_dir) {
+            fs::write(src_dir.join(REVISION_FILE), format!("{revision}\n"))
+                .expect("Failed to write template revision");
+        }
+
         // Remove the .git directory to avoid nested repo issues
         let git_dir = src_dir.join(".git");
         if git_dir.exists() {
@@ -38,9 +56,11 @@ fn main() {
 
     // Collect all .gitignore files recursively
     let mut templates: Vec<(PathBuf, String)> = Vec::new(); // (source_path, bare_name)
-    collect_templates(src_dir, &mut templates);
+    collect_templates(src_dir, &mut templates)
+        .unwrap_or_else(|e| panic!("failed to read templates in {}: {e}", src_dir.display()));
 
     // Build destination filename using scope-based prefixing
+    let mut manifest = String::new();
     for (src_path, bare_name) in &templates {
         let rel = src_path
             .strip_prefix(src_dir)
@@ -49,63 +69,37 @@ fn main() {
         let dest_name = compute_dest_name(rel, bare_name);
         let dest_path = dest_dir.join(&dest_name);
         fs::copy(src_path, dest_path).unwrap();
+        manifest.push_str(&format!("{dest_name}\t{}\n", upstream_path(rel)));
     }
+    fs::write(out_dir.join(UPSTREAM_MANIFEST), manifest)
+        .expect("Failed to write upstream manifest");
 
-    // Tell Cargo to re-run if templates change
-    println!("cargo::rerun-if-changed=templates");
-}
-
-/// Compute the destination filename based on the template's scope.
-///
-/// - Top-level: `{name}.gitignore`
-/// - Global/: `global.{name}.gitignore`
-/// - community/{subcategory}/: `community.{subcategory}.{name}.gitignore`
-/// - community/ (direct): `community.{name}.gitignore`
-fn compute_dest_name(rel_path: &Path, bare_name: &str) -> String {
-    let components: Vec<&str> = rel_path
-        .parent()
-        .unwrap_or(Path::new(""))
-        .components()
-        .filter_map(|c| c.as_os_str().to_str())
-        .collect();
-
-    if components.is_empty() {
-        // Top-level template
-        format!("{bare_name}{GITIGNORE_SUFFIX}")
-    } else if components[0].eq_ignore_ascii_case("global") {
-        format!("global.{bare_name}{GITIGNORE_SUFFIX}")
-    } else if components[0].eq_ignore_ascii_case("community") {
-        if components.len() > 1 {
-            // community/{subcategory}/{name}.gitignore
-            let subcategory = components[1];
-            format!("community.{subcategory}.{bare_name}{GITIGNORE_SUFFIX}")
-        } else {
-            // community/{name}.gitignore (no subcategory)
-            format!("community.{bare_name}{GITIGNORE_SUFFIX}")
-        }
-    } else {
-        // Unknown subdirectory — treat like top-level with prefix
-        let prefix = components.join(".");
-        format!("{prefix}.{bare_name}{GITIGNORE_SUFFIX}")
+    // Record which revision of the templates is embedded
+    let revision = git_revision(src_dir).or_else(|| {
+        let manifest = fs::read_to_string(src_dir.join(REVISION_FILE)).ok()?;
+        Some(manifest.trim().to_string())
+    });
+    if let Some((commit, date)) = revision.as_deref().and_then(|r| r.split_once(' ')) {
+        println!("cargo::rustc-env=GIG_TEMPLATES_COMMIT={commit}");
+        println!("cargo::rustc-env=GIG_TEMPLATES_DATE={date}");
     }
+
+    // Tell Cargo to re-run if templates or the naming rules change
+    println!("cargo::rerun-if-changed=templates");
+    println!("cargo::rerun-if-changed=src/layout.rs");
 }
 
-fn collect_templates(dir: &Path, templates: &mut Vec<(PathBuf, String)>) {
-    let entries = fs::read_dir(dir)
-        .unwrap_or_else(|e| panic!("failed to read directory {}: {e}", dir.display()));
-
-    for entry in entries {
-        let entry = entry.unwrap();
-        let path = entry.path();
-
-        if path.is_dir() {
-            collect_templates(&path, templates);
-        } else if let Some(name) = path.file_name().and_then(|n| n.to_str())
-            && let Some(bare) = name.strip_suffix(GITIGNORE_SUFFIX)
-            && !bare.is_empty()
-        {
-            let bare_owned = bare.to_string();
-            templates.push((path, bare_owned));
-        }
+/// `<commit> <commit date>` of the checkout in `dir`, if it is a git repository.
+fn git_revision(dir: &Path) -> Option<String> {
+    if !dir.join(".git").exists() {
+        return None;
     }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "-1", "--format=%H %cI"])
+        .output()
+        .ok()?;
+    let revision = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !revision.is_empty()).then_some(revision)
 }
@@ -0,0 +1,847 @@
+use std::collections::HashSet;
+
+use crate::matcher;
+use crate::merge::{Dedupe, Dropped, LineKind, Seen, push_deduped};
+
+const BLOCK_START: &str = "# >>> gig: ";
+const BLOCK_END: &str = "# <<< gig: ";
+/// Start of the optional provenance comment on a generated file's first line.
+pub const HEADER_PREFIX: &str = "# Generated by gig ";
+/// Start of an `--annotate` comment naming the later templates that also had
+/// the pattern below it.
+pub const ALSO_IN_PREFIX: &str = "# gig: also in ";
+
+/// A section of a .gitignore file.
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    /// Hand-written lines outside any managed block, kept verbatim.
+    Text(Vec<String>),
+    /// Lines between a pair of gig markers for the named template.
+    Block { name: String, lines: Vec<String> },
+}
+
+/// Split file content into hand-written text and gig-managed blocks.
+pub fn parse_segments(content: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text: Vec<String> = Vec::new();
+    // (name, lines, line number of the start marker)
+    let mut block: Option<(String, Vec<String>, usize)> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let lineno = i + 1;
+        let trimmed = line.trim_end();
+
+        if let Some(name) = trimmed.strip_prefix(BLOCK_START) {
+            if let Some((open, _, start)) = &block {
+                return Err(format!(
+                    "line {lineno}: gig block \"{name}\" starts inside block \"{open}\" opened on line {start}"
+                ));
+            }
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            block = Some((name.trim().to_lowercase(), Vec::new(), lineno));
+        } else if let Some(name) = trimmed.strip_prefix(BLOCK_END) {
+            let name = name.trim().to_lowercase();
+            match block.take() {
+                Some((open, lines, _)) if open == name => {
+                    segments.push(Segment::Block { name: open, lines });
+                }
+                Some((open, _, start)) => {
+                    return Err(format!(
+                        "line {lineno}: end of gig block \"{name}\" does not match block \"{open}\" opened on line {start}"
+                    ));
+                }
+                None => {
+                    return Err(format!(
+                        "line {lineno}: end of gig block \"{name}\" without a matching start"
+                    ));
+                }
+            }
+        } else if let Some((_, lines, _)) = &mut block {
+            lines.push(line.to_string());
+        } else {
+            text.push(line.to_string());
+        }
+    }
+
+    if let Some((name, _, start)) = block {
+        return Err(format!(
+            "line {start}: gig block \"{name}\" is never closed"
+        ));
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    Ok(segments)
+}
+
+/// Merge named templates into existing content as managed blocks.
+///
+/// Blocks already present in `existing` are regenerated in place, new ones are
+/// appended at the end, and hand-written lines are left untouched. Patterns
+/// in regenerated blocks are deduplicated against everything above them.
+pub fn merge_blocks(existing: Option<&str>, templates: &[(&str, &str)]) -> Result<String, String> {
+    merge_blocks_with(existing, templates, Dedupe::Exact, false).map(|(content, _)| content)
+}
+
+/// `merge_blocks` with a choice of deduplication, also returning the lines
+/// left out because an earlier, different line covers them. With `annotate`,
+/// each pattern written that later templates also had gets a comment above
+/// it naming them.
+pub fn merge_blocks_with(
+    existing: Option<&str>,
+    templates: &[(&str, &str)],
+    dedupe: Dedupe,
+    annotate: bool,
+) -> Result<(String, Vec<Dropped>), String> {
+    let segments = match existing {
+        Some(content) => parse_segments(content)?,
+        None => Vec::new(),
+    };
+
+    let mut seen = Seen::new(dedupe);
+    let mut regenerated: HashSet<&str> = HashSet::new();
+    let mut output = String::new();
+
+    for segment in &segments {
+        match segment {
+            Segment::Text(lines) => {
+                seen.extend(lines.iter().filter_map(|l| pattern_of(l)));
+                push_lines(&mut output, lines);
+            }
+            Segment::Block { name, lines } => {
+                push_marker(&mut output, BLOCK_START, name);
+                match templates.iter().find(|(n, _)| *n == name) {
+                    Some(&(n, content)) => {
+                        regenerated.insert(n);
+                        push_deduped(&mut output, n, content, &mut seen);
+                    }
+                    None => {
+                        seen.extend(lines.iter().filter_map(|l| pattern_of(l)));
+                        push_lines(&mut output, lines);
+                    }
+                }
+                push_marker(&mut output, BLOCK_END, name);
+            }
+        }
+    }
+
+    for &(name, content) in templates {
+        if !regenerated.insert(name) {
+            continue;
+        }
+        if !output.is_empty() && !output.ends_with("\n\n") {
+            output.push('\n');
+        }
+        push_marker(&mut output, BLOCK_START, name);
+        push_deduped(&mut output, name, content, &mut seen);
+        push_marker(&mut output, BLOCK_END, name);
+    }
+
+    if annotate {
+        // Back to front, so earlier offsets stay valid
+        for provenance in seen.provenance.iter().rev() {
+            if let Some(offset) = provenance.offset
+                && !provenance.also.is_empty()
+            {
+                let (pattern, _) = output[offset..].split_once('\n').unwrap_or_default();
+                output.insert_str(offset, &annotation(pattern.trim(), &provenance.also));
+            }
+        }
+    }
+
+    Ok((output, seen.dropped))
+}
+
+/// The `--annotate` comment line for `pattern`, repeated by `also` as
+/// (template, line as written there).
+fn annotation(pattern: &str, also: &[(&str, &str)]) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for &(name, line) in also {
+        let name = if line == pattern {
+            name.to_string()
+        } else {
+            format!("{name} (\"{line}\")")
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    format!("{ALSO_IN_PREFIX}{}\n", names.join(", "))
+}
+
+/// The templates listed in an `--annotate` comment, if `line` is one.
+pub fn annotation_of(line: &str) -> Option<&str> {
+    line.trim_end().strip_prefix(ALSO_IN_PREFIX)
+}
+
+/// What `remove_templates` took out of a file for one template.
+#[derive(Debug, PartialEq)]
+pub enum Removed {
+    /// The template's managed block was dropped, with this many lines inside it.
+    Block(usize),
+    /// This many hand-written lines matching the template were dropped.
+    Lines(usize),
+}
+
+/// Remove templates from existing content.
+///
+/// A template with a managed block loses the whole block. Otherwise, pattern
+/// lines outside blocks that appear in the template (looked up with `lookup`)
+/// are dropped, except patterns that a remaining block or one of the `kept`
+/// templates still contains; comments stay, since they may be hand-written. Blocks
+/// after the first change are regenerated so patterns they previously
+/// deduplicated against the removed lines come back; `lookup` must return
+/// templates as the file has them (translated for its format), and `dedupe`
+/// and `annotate` are the settings the file was written with.
+pub fn remove_templates(
+    existing: &str,
+    names: &[&str],
+    kept: &[&str],
+    lookup: &dyn Fn(&str) -> Result<String, String>,
+    dedupe: Dedupe,
+    annotate: bool,
+) -> Result<(String, Vec<(String, Removed)>), String> {
+    let mut segments = parse_segments(existing)?;
+    let mut report = Vec::new();
+    let mut first_change: Option<usize> = None;
+
+    // Drop managed blocks first so their patterns don't protect anything
+    let mut legacy: Vec<&str> = Vec::new();
+    for &name in names {
+        let found = segments
+            .iter()
+            .position(|s| matches!(s, Segment::Block { name: n, .. } if n == name));
+        match found {
+            Some(i) => {
+                let Segment::Block { lines, .. } = segments.remove(i) else {
+                    unreachable!("position matched a block");
+                };
+                drop_separator(&mut segments, i);
+                // The separator may have taken the previous segment with it
+                let i = i.saturating_sub(1);
+                first_change = Some(first_change.map_or(i, |c| c.min(i)));
+                report.push((name.to_string(), Removed::Block(lines.len())));
+            }
+            None => legacy.push(name),
+        }
+    }
+
+    // Remaining names remove matching hand-written lines
+    if !legacy.is_empty() {
+        let mut protected: HashSet<String> = segments
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Block { lines, .. } => Some(lines),
+                Segment::Text(_) => None,
+            })
+            .flatten()
+            .filter_map(|l| pattern_of(l).map(str::to_string))
+            .collect();
+        for name in kept.iter().filter(|n| !names.contains(n)) {
+            if let Ok(template) = lookup(name) {
+                protected.extend(template.lines().filter_map(pattern_of).map(str::to_string));
+            }
+        }
+
+        for name in legacy {
+            let template = lookup(name)?;
+            let targets: HashSet<&str> = template
+                .lines()
+                .filter_map(pattern_of)
+                .filter(|l| !protected.contains(*l))
+                .collect();
+
+            let mut count = 0;
+            for (i, segment) in segments.iter_mut().enumerate() {
+                if let Segment::Text(lines) = segment {
+                    let removed = remove_lines(lines, &targets);
+                    if removed > 0 {
+                        count += removed;
+                        first_change = Some(first_change.map_or(i, |c| c.min(i)));
+                    }
+                }
+            }
+            report.push((name.to_string(), Removed::Lines(count)));
+        }
+        segments.retain(|s| !matches!(s, Segment::Text(lines) if lines.is_empty()));
+    }
+
+    let content = render_segments(&segments);
+    let Some(first_change) = first_change else {
+        return Ok((content, report));
+    };
+
+    // Regenerate later blocks whose templates are still known
+    let mut later: Vec<(&str, String)> = Vec::new();
+    for segment in segments.iter().skip(first_change) {
+        if let Segment::Block { name, .. } = segment
+            && let Ok(template) = lookup(name)
+        {
+            later.push((name, template));
+        }
+    }
+    let later: Vec<(&str, &str)> = later.iter().map(|(n, t)| (*n, t.as_str())).collect();
+    let (content, _) = merge_blocks_with(Some(&content), &later, dedupe, annotate)?;
+
+    Ok((content, report))
+}
+
+/// Names of the managed blocks in `content`, in file order.
+pub fn block_names(content: &str) -> Result<Vec<String>, String> {
+    let segments = parse_segments(content)?;
+    Ok(segments
+        .into_iter()
+        .filter_map(|s| match s {
+            Segment::Block { name, .. } => Some(name),
+            Segment::Text(_) => None,
+        })
+        .collect())
+}
+
+/// For each line of `content`, the name of the managed block it is inside.
+pub fn line_blocks(content: &str) -> Vec<Option<&str>> {
+    let mut current = None;
+    content
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_end();
+            if let Some(name) = trimmed.strip_prefix(BLOCK_START) {
+                current = Some(name.trim());
+                None
+            } else if trimmed.starts_with(BLOCK_END) {
+                current = None;
+                None
+            } else {
+                current
+            }
+        })
+        .collect()
+}
+
+/// Render segments back into file content.
+pub fn render_segments(segments: &[Segment]) -> String {
+    let mut output = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(lines) => push_lines(&mut output, lines),
+            Segment::Block { name, lines } => {
+                push_marker(&mut output, BLOCK_START, name);
+                push_lines(&mut output, lines);
+                push_marker(&mut output, BLOCK_END, name);
+            }
+        }
+    }
+    output
+}
+
+/// A line from one template that overrides a line from another: a negation
+/// re-including what an earlier template ignores, or a pattern ignoring what
+/// an earlier template re-includes.
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub earlier: ConflictLine,
+    pub later: ConflictLine,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConflictLine {
+    pub block: String,
+    /// 1-based line number in the content.
+    pub line: usize,
+    pub pattern: String,
+}
+
+/// Find lines in one block that override lines from an earlier, different
+/// block. Each overriding line is reported once, against the closest line
+/// it overrides.
+pub fn conflicts(content: &str) -> Vec<Conflict> {
+    let lines: Vec<(usize, &str, &str)> = content
+        .lines()
+        .zip(line_blocks(content))
+        .enumerate()
+        .filter_map(|(i, (line, block))| Some((i + 1, block?, pattern_of(line)?)))
+        .collect();
+
+    let mut conflicts = Vec::new();
+    for (i, &(line, block, pattern)) in lines.iter().enumerate() {
+        let negated = pattern.starts_with('!');
+        let earlier = lines[..i].iter().rev().find(|&&(_, b, p)| {
+            b != block && p.starts_with('!') != negated && matcher::overlaps(p, pattern)
+        });
+        if let Some(&(earlier_line, earlier_block, earlier_pattern)) = earlier {
+            conflicts.push(Conflict {
+                earlier: ConflictLine {
+                    block: earlier_block.to_string(),
+                    line: earlier_line,
+                    pattern: earlier_pattern.to_string(),
+                },
+                later: ConflictLine {
+                    block: block.to_string(),
+                    line,
+                    pattern: pattern.to_string(),
+                },
+            });
+        }
+    }
+    conflicts
+}
+
+/// Put `header` on the first line of `content`, replacing a gig header that is
+/// already there. Content without one only gets a header if `add` is set.
+pub fn set_header(content: &str, header: &str, add: bool) -> String {
+    let (first, rest) = content.split_once('\n').unwrap_or((content, ""));
+    if first.starts_with(HEADER_PREFIX) {
+        format!("{header}\n{rest}")
+    } else if add {
+        format!("{header}\n{content}")
+    } else {
+        content.to_string()
+    }
+}
+
+/// After removing the segment at `index`, drop the blank line that separated it
+/// from its neighbours so removal doesn't leave a gap behind.
+fn drop_separator(segments: &mut Vec<Segment>, index: usize) {
+    if index == 0 {
+        if let Some(Segment::Text(lines)) = segments.first_mut()
+            && lines.first().is_some_and(|l| l.trim().is_empty())
+        {
+            lines.remove(0);
+            if lines.is_empty() {
+                segments.remove(0);
+            }
+        }
+        return;
+    }
+
+    let next_is_separated = match segments.get(index) {
+        None | Some(Segment::Block { .. }) => true,
+        Some(Segment::Text(lines)) => lines.first().is_some_and(|l| l.trim().is_empty()),
+    };
+    if !next_is_separated {
+        return;
+    }
+    if let Segment::Text(lines) = &mut segments[index - 1]
+        && lines.last().is_some_and(|l| l.trim().is_empty())
+    {
+        lines.pop();
+        if lines.is_empty() {
+            segments.remove(index - 1);
+        }
+    }
+}
+
+/// Remove lines whose trimmed text is in `targets`, along with blank lines
+/// left dangling by the removal. Returns the number of target lines removed.
+fn remove_lines(lines: &mut Vec<String>, targets: &HashSet<&str>) -> usize {
+    let mut kept: Vec<String> = Vec::with_capacity(lines.len());
+    let mut removed = 0;
+    let mut dangling = false;
+
+    for line in lines.drain(..) {
+        let trimmed = line.trim();
+        if targets.contains(trimmed) {
+            removed += 1;
+            dangling = true;
+            continue;
+        }
+        if trimmed.is_empty() && dangling && kept.last().is_none_or(|l| l.trim().is_empty()) {
+            continue;
+        }
+        if !trimmed.is_empty() {
+            dangling = false;
+        }
+        kept.push(line);
+    }
+
+    *lines = kept;
+    removed
+}
+
+/// Return the pattern on a line as git reads it, or None for blanks and
+/// comments.
+pub fn pattern_of(line: &str) -> Option<&str> {
+    match LineKind::of(line) {
+        LineKind::Pattern(pattern) => Some(pattern),
+        LineKind::Blank | LineKind::Comment => None,
+    }
+}
+
+fn push_lines(output: &mut String, lines: &[String]) {
+    for line in lines {
+        output.push_str(line);
+        output.push('\n');
+    }
+}
+
+fn push_marker(output: &mut String, prefix: &str, name: &str) {
+    output.push_str(prefix);
+    output.push_str(name);
+    output.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::{self, Format};
+
+    #[test]
+    fn test_parse_segments_text_only() {
+        let segments = parse_segments("*.log\n# note\n").unwrap();
+        assert_eq!(
+            segments,
+            vec![Segment::Text(vec!["*.log".into(), "# note".into()])]
+        );
+    }
+
+    #[test]
+    fn test_parse_segments_with_block() {
+        let content = "custom/\n# >>> gig: python\n*.pyc\n# <<< gig: python\nafter/\n";
+        let segments = parse_segments(content).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Text(vec!["custom/".into()]),
+                Segment::Block {
+                    name: "python".into(),
+                    lines: vec!["*.pyc".into()]
+                },
+                Segment::Text(vec!["after/".into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_segments_unterminated_block() {
+        let result = parse_segments("# >>> gig: python\n*.pyc\n");
+        assert!(result.unwrap_err().contains("never closed"));
+    }
+
+    #[test]
+    fn test_parse_segments_mismatched_end() {
+        let result = parse_segments("# >>> gig: python\n# <<< gig: go\n");
+        assert!(result.unwrap_err().contains("does not match"));
+    }
+
+    #[test]
+    fn test_parse_segments_nested_start() {
+        let result = parse_segments("# >>> gig: python\n# >>> gig: go\n");
+        assert!(result.unwrap_err().contains("starts inside"));
+    }
+
+    #[test]
+    fn test_parse_segments_end_without_start() {
+        let result = parse_segments("# <<< gig: go\n");
+        assert!(result.unwrap_err().contains("without a matching start"));
+    }
+
+    fn lookup(name: &str) -> Result<String, String> {
+        match name {
+            "go" => Ok("# Go\n*.exe\n*.so\n".to_string()),
+            "c" => Ok("# C\n*.so\n*.o\n".to_string()),
+            "node" => Ok("# Logs\n*.log\nnode_modules/\n".to_string()),
+            _ => Err(format!("no template found for language \"{name}\"")),
+        }
+    }
+
+    fn remove(existing: &str, names: &[&str]) -> Result<(String, Vec<(String, Removed)>), String> {
+        remove_templates(existing, names, &[], &lookup, Dedupe::Exact, false)
+    }
+
+    #[test]
+    fn test_render_segments_round_trip() {
+        let content = "custom/\n# >>> gig: python\n*.pyc\n# <<< gig: python\n\nafter/\n";
+        let segments = parse_segments(content).unwrap();
+        assert_eq!(render_segments(&segments), content);
+    }
+
+    #[test]
+    fn test_remove_templates_drops_block() {
+        let existing = merge_blocks(Some("mine/\n"), &[("go", "*.exe\n")]).unwrap();
+        let (content, report) = remove(&existing, &["go"]).unwrap();
+        assert_eq!(content, "mine/\n");
+        assert_eq!(report, vec![("go".to_string(), Removed::Block(1))]);
+    }
+
+    #[test]
+    fn test_remove_templates_restores_deduplicated_patterns() {
+        let existing = merge_blocks(
+            None,
+            &[("go", &lookup("go").unwrap()), ("c", &lookup("c").unwrap())],
+        )
+        .unwrap();
+        assert_eq!(existing.matches("*.so").count(), 1);
+
+        let (content, _) = remove(&existing, &["go"]).unwrap();
+        assert_eq!(content, "# >>> gig: c\n# C\n*.so\n*.o\n# <<< gig: c\n");
+    }
+
+    #[test]
+    fn test_remove_templates_keeps_format_of_later_blocks() {
+        let docker =
+            |name: &str| lookup(name).map(|t| convert::translate(&t, Format::Dockerignore).content);
+        let templates = [("go", docker("go").unwrap()), ("c", docker("c").unwrap())];
+        let named: Vec<(&str, &str)> = templates.iter().map(|(n, t)| (*n, t.as_str())).collect();
+        let existing = merge_blocks(None, &named).unwrap();
+
+        let (content, _) =
+            remove_templates(&existing, &["go"], &[], &docker, Dedupe::Exact, false).unwrap();
+        assert_eq!(
+            content,
+            "# >>> gig: c\n# C\n**/*.so\n**/*.o\n# <<< gig: c\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_templates_legacy_lines() {
+        let existing = "# Logs\n*.log\nnode_modules/\n\nmine/\n";
+        let (content, report) = remove(existing, &["node"]).unwrap();
+        assert_eq!(content, "# Logs\n\nmine/\n");
+        assert_eq!(report, vec![("node".to_string(), Removed::Lines(2))]);
+    }
+
+    #[test]
+    fn test_remove_templates_legacy_keeps_patterns_of_kept_templates() {
+        let existing = "# Go\n*.exe\n*.so\n# C\n*.o\n";
+        let (content, report) =
+            remove_templates(existing, &["go"], &["c"], &lookup, Dedupe::Exact, false).unwrap();
+        assert_eq!(content, "# Go\n*.so\n# C\n*.o\n");
+        assert_eq!(report, vec![("go".to_string(), Removed::Lines(1))]);
+    }
+
+    #[test]
+    fn test_remove_templates_keeps_patterns_needed_by_blocks() {
+        let existing = "*.so\n*.o\n# >>> gig: go\n*.exe\n*.so\n# <<< gig: go\n";
+        let (content, _) = remove(existing, &["c"]).unwrap();
+        assert!(content.starts_with("*.so\n# >>> gig: go\n"));
+        assert!(!content.contains("*.o\n"));
+    }
+
+    #[test]
+    fn test_remove_templates_unknown_name() {
+        let result = remove("mine/\n", &["nope"]);
+        assert!(result.unwrap_err().contains("no template found"));
+    }
+
+    #[test]
+    fn test_remove_templates_nothing_to_remove() {
+        let (content, report) = remove("mine/\n", &["go"]).unwrap();
+        assert_eq!(content, "mine/\n");
+        assert_eq!(report, vec![("go".to_string(), Removed::Lines(0))]);
+    }
+
+    #[test]
+    fn test_merge_blocks_new_file() {
+        let result = merge_blocks(None, &[("python", "*.pyc\n"), ("go", "*.exe\n")]).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: python\n*.pyc\n# <<< gig: python\n\n# >>> gig: go\n*.exe\n# <<< gig: go\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_deduplicates_across_blocks() {
+        let result = merge_blocks(None, &[("go", "*.exe\n*.so\n"), ("c", "*.so\n*.o\n")]).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: go\n*.exe\n*.so\n# <<< gig: go\n\n# >>> gig: c\n*.o\n# <<< gig: c\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_regenerates_in_place() {
+        let existing = "top/\n# >>> gig: python\n*.old\n# <<< gig: python\nbottom/\n";
+        let result = merge_blocks(Some(existing), &[("python", "*.pyc\n")]).unwrap();
+        assert_eq!(
+            result,
+            "top/\n# >>> gig: python\n*.pyc\n# <<< gig: python\nbottom/\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_keeps_other_blocks() {
+        let existing = "# >>> gig: go\n*.exe\n# <<< gig: go\n";
+        let result = merge_blocks(Some(existing), &[("python", "*.pyc\n")]).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: go\n*.exe\n# <<< gig: go\n\n# >>> gig: python\n*.pyc\n# <<< gig: python\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_hand_written_patterns_win_dedup() {
+        let existing = "# mine\n*.log\n";
+        let result = merge_blocks(Some(existing), &[("node", "*.log\nnode_modules/\n")]).unwrap();
+        assert_eq!(
+            result,
+            "# mine\n*.log\n\n# >>> gig: node\nnode_modules/\n# <<< gig: node\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_rerun_is_stable() {
+        let first = merge_blocks(None, &[("go", "*.exe\n"), ("c", "*.o\n")]).unwrap();
+        let second = merge_blocks(Some(&first), &[("go", "*.exe\n"), ("c", "*.o\n")]).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_merge_blocks_invalid_existing() {
+        let result = merge_blocks(Some("# >>> gig: go\n"), &[("go", "*.exe\n")]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_block_names_in_file_order() {
+        let content = "a/\n# >>> gig: node\nx\n# <<< gig: node\n# >>> gig: go\n# <<< gig: go\n";
+        assert_eq!(block_names(content).unwrap(), vec!["node", "go"]);
+    }
+
+    #[test]
+    fn test_line_blocks() {
+        let content = "a/\n# >>> gig: go\nx\n# <<< gig: go\nb/\n";
+        assert_eq!(
+            line_blocks(content),
+            vec![None, None, Some("go"), None, None]
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_keeps_repeat_after_negation() {
+        let content = merge_blocks(None, &[("a", "*.foo\n!keep.foo\n"), ("b", "*.foo\n")]).unwrap();
+        assert_eq!(
+            content,
+            "# >>> gig: a\n*.foo\n!keep.foo\n# <<< gig: a\n\n# >>> gig: b\n*.foo\n# <<< gig: b\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_keeps_repeat_after_wildcard_negation() {
+        // Dropping b's foo* would leave foobar re-included by !*bar
+        let content = merge_blocks(None, &[("a", "foo*\n!*bar\n"), ("b", "foo*\n")]).unwrap();
+        assert_eq!(
+            content,
+            "# >>> gig: a\nfoo*\n!*bar\n# <<< gig: a\n\n# >>> gig: b\nfoo*\n# <<< gig: b\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_blocks_semantic_dedupe() {
+        let templates = [
+            ("rust", "target\n*.rs.bk\n"),
+            ("team", "/target/\n**/*.rs.bk\nbuild # comment\n"),
+        ];
+        let (content, dropped) =
+            merge_blocks_with(None, &templates, Dedupe::Semantic, false).unwrap();
+        assert_eq!(
+            content,
+            "# >>> gig: rust\ntarget\n*.rs.bk\n# <<< gig: rust\n\n# >>> gig: team\nbuild # comment\n# <<< gig: team\n"
+        );
+        assert_eq!(
+            dropped,
+            vec![
+                Dropped {
+                    pattern: "/target/".to_string(),
+                    covered_by: "target".to_string(),
+                },
+                Dropped {
+                    pattern: "**/*.rs.bk".to_string(),
+                    covered_by: "*.rs.bk".to_string(),
+                },
+            ]
+        );
+
+        // Exact mode keeps them all
+        let (_, dropped) = merge_blocks_with(None, &templates, Dedupe::Exact, false).unwrap();
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn test_merge_blocks_annotate() {
+        let templates = [
+            ("go", "# Binaries\n*.exe\n*.so\n"),
+            ("c", "*.so\n*.o\n"),
+            ("rust", "**/*.so\n*.exe\n"),
+        ];
+        let (content, _) = merge_blocks_with(None, &templates, Dedupe::Semantic, true).unwrap();
+        assert_eq!(
+            content,
+            "# >>> gig: go\n# Binaries\n# gig: also in rust\n*.exe\n# gig: also in c, rust (\"**/*.so\")\n*.so\n# <<< gig: go\n\n\
+             # >>> gig: c\n*.o\n# <<< gig: c\n\n\
+             # >>> gig: rust\n# <<< gig: rust\n"
+        );
+        assert_eq!(annotation_of("# gig: also in c, rust"), Some("c, rust"));
+        assert_eq!(annotation_of("*.so"), None);
+
+        // Regenerating replaces the annotations rather than stacking them
+        let (again, _) =
+            merge_blocks_with(Some(&content), &templates, Dedupe::Semantic, true).unwrap();
+        assert_eq!(again, content);
+    }
+
+    #[test]
+    fn test_merge_blocks_semantic_respects_negation() {
+        let templates = [("a", "*.log\n!keep.log\n"), ("b", "/keep.log\n")];
+        let (_, dropped) = merge_blocks_with(None, &templates, Dedupe::Semantic, false).unwrap();
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn test_conflicts_between_templates() {
+        let content = merge_blocks(
+            None,
+            &[
+                ("unity", "*.meta\n!important.meta\n"),
+                ("editor", "!*.meta\n"),
+                ("other", "*.log\n!keep.log\n"),
+            ],
+        )
+        .unwrap();
+
+        let found = conflicts(&content);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].earlier.block, "unity");
+        assert_eq!(found[0].earlier.pattern, "*.meta");
+        assert_eq!(found[0].later.block, "editor");
+        assert_eq!(found[0].later.pattern, "!*.meta");
+    }
+
+    #[test]
+    fn test_conflicts_between_wildcards() {
+        let content = merge_blocks(None, &[("a", "foo*\n"), ("b", "!*bar\n")]).unwrap();
+        let found = conflicts(&content);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].earlier.pattern, "foo*");
+        assert_eq!(found[0].later.pattern, "!*bar");
+    }
+
+    #[test]
+    fn test_conflicts_ignores_hand_written_lines() {
+        let content = "# >>> gig: node\n*.log\n# <<< gig: node\n!keep.log\n";
+        assert!(conflicts(content).is_empty());
+    }
+
+    const HEADER: &str =
+        "# Generated by gig 1.0.0 from github/gitignore at 0123abcd4567 (2024-05-01)";
+
+    #[test]
+    fn test_set_header_adds_on_request() {
+        let content = "# >>> gig: go\n*.exe\n# <<< gig: go\n";
+        assert_eq!(set_header(content, HEADER, false), content);
+        assert_eq!(
+            set_header(content, HEADER, true),
+            format!("{HEADER}\n{content}")
+        );
+    }
+
+    #[test]
+    fn test_set_header_replaces_existing() {
+        let content = "# Generated by gig 0.1.0 from github/gitignore at 1111\ncustom/\n";
+        let expected = format!("{HEADER}\ncustom/\n");
+        assert_eq!(set_header(content, HEADER, false), expected);
+        assert_eq!(set_header(content, HEADER, true), expected);
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::toml;
+
+const CONFIG_FILE: &str = "gig/config.toml";
+
+/// User settings from `$XDG_CONFIG_HOME/gig/config.toml`.
+///
+/// ```toml
+/// template_path = ["~/work/gitignore-templates"]
+///
+/// [presets]
+/// team-rust = ["rust", "global.macos", "global.jetbrains"]
+///
+/// [aliases]
+/// js = "node"
+///
+/// [update]
+/// source = "https://mirror.example.com/gitignore.git"
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    /// Named lists of templates, used on the command line as `@name`.
+    pub presets: BTreeMap<String, Vec<String>>,
+    /// Alternative names for templates or presets.
+    pub aliases: BTreeMap<String, String>,
+    /// Extra directories of `.gitignore` templates, laid out like github/gitignore.
+    pub template_path: Vec<PathBuf>,
+    /// Where `gig update` fetches templates from, if not the upstream repository.
+    pub update_source: Option<String>,
+}
+
+impl Config {
+    /// Load the user config, or an empty one if there is no config file.
+    pub fn load() -> Result<Config, String> {
+        match config_path() {
+            Some(path) => Config::load_from(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Load config from `path`, or an empty one if the file doesn't exist.
+    /// Relative `template_path` entries are resolved against the file's directory.
+    pub fn load_from(path: &Path) -> Result<Config, String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+        let mut config = Config::parse(&content).map_err(|e| format!("{}: {e}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            for entry in &mut config.template_path {
+                if entry.is_relative() {
+                    *entry = dir.join(&*entry);
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn parse(content: &str) -> Result<Config, String> {
+        let doc = toml::parse(content)?;
+        let mut config = Config::default();
+
+        if let Some(value) = doc.root.get("template_path") {
+            let dirs = value
+                .as_string_array()
+                .ok_or("template_path: expected an array of strings")?;
+            config.template_path = dirs.iter().map(|d| expand_home(d)).collect();
+        }
+
+        if let Some(presets) = doc.tables.get("presets") {
+            for (name, value) in presets {
+                let templates = value
+                    .as_string_array()
+                    .ok_or_else(|| format!("presets.{name}: expected an array of strings"))?;
+                let name = name.strip_prefix('@').unwrap_or(name).to_lowercase();
+                config.presets.insert(name, templates);
+            }
+        }
+
+        if let Some(aliases) = doc.tables.get("aliases") {
+            for (name, value) in aliases {
+                let target = value
+                    .as_str()
+                    .ok_or_else(|| format!("aliases.{name}: expected a string"))?;
+                config
+                    .aliases
+                    .insert(name.to_lowercase(), target.to_string());
+            }
+        }
+
+        if let Some(update) = doc.tables.get("update")
+            && let Some(value) = update.get("source")
+        {
+            let source = value.as_str().ok_or("update.source: expected a string")?;
+            config.update_source = Some(source.to_string());
+        }
+
+        Ok(config)
+    }
+
+    /// Expand presets (`@name`) and aliases recursively, dropping repeated names.
+    pub fn expand(&self, names: &[String]) -> Result<Vec<String>, String> {
+        let mut expanded = Vec::new();
+        let mut stack = Vec::new();
+        for name in names {
+            self.expand_into(name, &mut stack, &mut expanded)?;
+        }
+        Ok(expanded)
+    }
+
+    fn expand_into(
+        &self,
+        name: &str,
+        stack: &mut Vec<String>,
+        expanded: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let lower = name.to_lowercase();
+        let targets: &[String] = if let Some(preset) = lower.strip_prefix('@') {
+            self.presets
+                .get(preset)
+                .ok_or_else(|| format!("unknown preset \"{name}\""))?
+        } else if let Some(target) = self.aliases.get(&lower) {
+            std::slice::from_ref(target)
+        } else {
+            if !expanded.iter().any(|e| e.eq_ignore_ascii_case(name)) {
+                expanded.push(name.to_string());
+            }
+            return Ok(());
+        };
+
+        if let Some(start) = stack.iter().position(|s| *s == lower) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(lower);
+            return Err(format!("cycle in presets/aliases: {}", cycle.join(" -> ")));
+        }
+
+        stack.push(lower);
+        for target in targets {
+            self.expand_into(target, stack, expanded)?;
+        }
+        stack.pop();
+        Ok(())
+    }
+}
+
+/// Expand a leading `~/` to the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Path of the user config file.
+pub fn config_path() -> Option<PathBuf> {
+    config_home().map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn config_home() -> Option<PathBuf> {
+    match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if Path::new(&dir).is_absolute() => Some(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    const SAMPLE: &str = r#"
+[presets]
+team-rust = ["rust", "@editors", "js"]
+editors = ["global.jetbrains", "global.visualstudiocode"]
+
+[aliases]
+js = "node"
+"#;
+
+    #[test]
+    fn test_parse_presets_and_aliases() {
+        let config = Config::parse(SAMPLE).unwrap();
+        assert_eq!(
+            config.presets["team-rust"],
+            names(&["rust", "@editors", "js"])
+        );
+        assert_eq!(config.aliases["js"], "node");
+    }
+
+    #[test]
+    fn test_parse_rejects_wrong_types() {
+        let err = Config::parse("[presets]\nx = \"rust\"\n").unwrap_err();
+        assert!(err.contains("presets.x"), "got: {err}");
+
+        let err = Config::parse("[aliases]\nx = [\"rust\"]\n").unwrap_err();
+        assert!(err.contains("aliases.x"), "got: {err}");
+    }
+
+    #[test]
+    fn test_parse_strips_at_from_preset_names() {
+        let config = Config::parse("[presets]\n\"@web\" = [\"node\"]\n").unwrap();
+        assert!(config.presets.contains_key("web"));
+    }
+
+    #[test]
+    fn test_expand_recursive() {
+        let config = Config::parse(SAMPLE).unwrap();
+        let result = config.expand(&names(&["@team-rust", "python"])).unwrap();
+        assert_eq!(
+            result,
+            names(&[
+                "rust",
+                "global.jetbrains",
+                "global.visualstudiocode",
+                "node",
+                "python"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_expand_drops_repeats() {
+        let config = Config::parse(SAMPLE).unwrap();
+        let result = config
+            .expand(&names(&["rust", "@team-rust", "JS"]))
+            .unwrap();
+        assert_eq!(result.iter().filter(|n| *n == "rust").count(), 1);
+        assert_eq!(result.iter().filter(|n| *n == "node").count(), 1);
+    }
+
+    #[test]
+    fn test_expand_unknown_preset() {
+        let config = Config::default();
+        let err = config.expand(&names(&["@missing"])).unwrap_err();
+        assert!(err.contains("unknown preset \"@missing\""));
+    }
+
+    #[test]
+    fn test_expand_detects_cycles() {
+        let config =
+            Config::parse("[presets]\na = [\"@b\"]\nb = [\"x\"]\n[aliases]\nx = \"@a\"\n").unwrap();
+        let err = config.expand(&names(&["@a"])).unwrap_err();
+        assert!(err.contains("@a -> @b -> x -> @a"), "got: {err}");
+    }
+
+    #[test]
+    fn test_parse_template_path() {
+        let config = Config::parse("template_path = [\"/opt/templates\", \"rel\"]\n").unwrap();
+        assert_eq!(
+            config.template_path,
+            vec![PathBuf::from("/opt/templates"), PathBuf::from("rel")]
+        );
+
+        let err = Config::parse("template_path = \"/opt\"\n").unwrap_err();
+        assert!(err.contains("template_path"), "got: {err}");
+    }
+
+    #[test]
+    fn test_parse_update_source() {
+        let config = Config::parse("[update]\nsource = \"file:///srv/gitignore\"\n").unwrap();
+        assert_eq!(
+            config.update_source.as_deref(),
+            Some("file:///srv/gitignore")
+        );
+
+        let err = Config::parse("[update]\nsource = [\"a\"]\n").unwrap_err();
+        assert!(err.contains("update.source"), "got: {err}");
+    }
+
+    #[test]
+    fn test_load_from_resolves_template_path_against_config_dir() {
+        let dir = std::env::temp_dir().join(format!("gig-config-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "template_path = [\"/opt/templates\", \"rel\"]\n").unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(
+            config.template_path,
+            vec![PathBuf::from("/opt/templates"), dir.join("rel")]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_from_missing_file_is_empty() {
+        let config = Config::load_from(Path::new("/nonexistent/gig/config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
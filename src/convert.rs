@@ -0,0 +1,433 @@
+//! Translating gitignore templates into other ignore-file formats, for
+//! `--format`.
+//!
+//! Most tools read gitignore syntax as is. Docker and Helm match with Go's
+//! `filepath.Match`, and Mercurial has its own glob rules, so their patterns
+//! are rewritten. A pattern that can't be expressed exactly gets a `# gig:`
+//! comment above it saying how it was approximated, or is left out with a
+//! comment saying why.
+
+use std::path::Path;
+
+use crate::{blocks, matcher};
+
+/// Prefix of the comments gig adds to explain a translation.
+pub const NOTE_PREFIX: &str = "# gig: ";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Gitignore,
+    Dockerignore,
+    Npmignore,
+    Prettierignore,
+    Eslintignore,
+    Gcloudignore,
+    Helmignore,
+    Hgignore,
+}
+
+pub const FORMATS: &[Format] = &[
+    Format::Gitignore,
+    Format::Dockerignore,
+    Format::Npmignore,
+    Format::Prettierignore,
+    Format::Eslintignore,
+    Format::Gcloudignore,
+    Format::Helmignore,
+    Format::Hgignore,
+];
+
+impl Format {
+    /// Parse a format name, with or without the leading dot.
+    pub fn parse(name: &str) -> Result<Format, String> {
+        let name = name.strip_prefix('.').unwrap_or(name).to_lowercase();
+        FORMATS
+            .iter()
+            .copied()
+            .find(|f| f.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = FORMATS.iter().map(|f| f.name()).collect();
+                format!(
+                    "unknown format \"{name}\" (expected one of: {})",
+                    names.join(", ")
+                )
+            })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Gitignore => "gitignore",
+            Format::Dockerignore => "dockerignore",
+            Format::Npmignore => "npmignore",
+            Format::Prettierignore => "prettierignore",
+            Format::Eslintignore => "eslintignore",
+            Format::Gcloudignore => "gcloudignore",
+            Format::Helmignore => "helmignore",
+            Format::Hgignore => "hgignore",
+        }
+    }
+
+    /// The format a file's name suggests, defaulting to gitignore.
+    pub fn for_path(path: &Path) -> Format {
+        let name = path.file_name().and_then(|n| n.to_str());
+        FORMATS
+            .iter()
+            .copied()
+            .find(|f| name == Some(f.file_name()))
+            .unwrap_or_default()
+    }
+
+    /// A line the file needs before any pattern, written once at the top
+    /// rather than in each template's block.
+    pub fn preamble(self) -> Option<&'static str> {
+        match self {
+            Format::Hgignore => Some("syntax: glob"),
+            _ => None,
+        }
+    }
+
+    /// The file this format is usually written to.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Format::Gitignore => ".gitignore",
+            Format::Dockerignore => ".dockerignore",
+            Format::Npmignore => ".npmignore",
+            Format::Prettierignore => ".prettierignore",
+            Format::Eslintignore => ".eslintignore",
+            Format::Gcloudignore => ".gcloudignore",
+            Format::Helmignore => ".helmignore",
+            Format::Hgignore => ".hgignore",
+        }
+    }
+}
+
+/// A template rewritten for another format.
+#[derive(Debug, PartialEq)]
+pub struct Translated {
+    pub content: String,
+    /// Patterns approximated or left out, each explained by a comment.
+    pub notes: usize,
+}
+
+/// How one pattern line comes out in the target format.
+enum Line {
+    Exact(String),
+    /// A close translation, and what differs.
+    Approx(String, &'static str),
+    /// No translation, and why.
+    Unsupported(&'static str),
+}
+
+/// Rewrite a gitignore template for `format`.
+pub fn translate(template: &str, format: Format) -> Translated {
+    let rule: fn(&Pattern) -> Line = match format {
+        Format::Dockerignore => docker,
+        Format::Helmignore => helm,
+        Format::Hgignore => hg,
+        // These read gitignore syntax
+        _ => {
+            return Translated {
+                content: template.to_string(),
+                notes: 0,
+            };
+        }
+    };
+
+    let mut content = String::new();
+    let mut notes = 0;
+    for line in template.lines() {
+        let text = matcher::trim_trailing_spaces(line);
+        if text.trim().is_empty() || text.starts_with('#') {
+            content.push_str(line);
+            content.push('\n');
+            continue;
+        }
+
+        let pattern = Pattern::parse(text);
+        let translated = if pattern.escaped_trailing_space() {
+            Line::Unsupported("trailing spaces are always trimmed")
+        } else {
+            rule(&pattern)
+        };
+        match translated {
+            Line::Exact(out) => content.push_str(&out),
+            Line::Approx(out, why) => {
+                content.push_str(&format!("{NOTE_PREFIX}approximated \"{text}\": {why}\n"));
+                content.push_str(&out);
+                notes += 1;
+            }
+            Line::Unsupported(why) => {
+                content.push_str(&format!("{NOTE_PREFIX}left out \"{text}\": {why}"));
+                notes += 1;
+            }
+        }
+        content.push('\n');
+    }
+
+    Translated { content, notes }
+}
+
+/// Start `content` with `format`'s preamble, after a gig header if there is
+/// one, unless the file already has it.
+pub fn add_preamble(content: &str, format: Format) -> String {
+    let Some(preamble) = format.preamble() else {
+        return content.to_string();
+    };
+    if content.lines().any(|l| l.trim() == preamble) {
+        return content.to_string();
+    }
+    match content.split_once('\n') {
+        Some((first, rest)) if first.starts_with(blocks::HEADER_PREFIX) => {
+            format!("{first}\n{preamble}\n{rest}")
+        }
+        _ => format!("{preamble}\n{content}"),
+    }
+}
+
+/// A gitignore pattern taken apart.
+struct Pattern<'a> {
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+    /// Without `!`, a leading `/` and a trailing `/`.
+    body: &'a str,
+}
+
+impl<'a> Pattern<'a> {
+    fn parse(text: &'a str) -> Pattern<'a> {
+        let (negated, mut body) = match text.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let dir_only = body.ends_with('/') && !body.ends_with("\\/");
+        if dir_only {
+            body = &body[..body.len() - 1];
+        }
+        let anchored = body.contains('/');
+        Pattern {
+            negated,
+            dir_only,
+            anchored,
+            body: body.strip_prefix('/').unwrap_or(body),
+        }
+    }
+
+    fn bang(&self) -> &'static str {
+        if self.negated { "!" } else { "" }
+    }
+
+    fn escaped_trailing_space(&self) -> bool {
+        self.body.ends_with("\\ ")
+    }
+}
+
+/// Go's `filepath.Match` negates a class with `^`, and has no named classes.
+fn go_glob(body: &str) -> Result<String, &'static str> {
+    if body.contains("[:") {
+        return Err("named character classes aren't supported");
+    }
+    Ok(body.replace("[!", "[^"))
+}
+
+/// Docker matches every pattern from the context root, with `**` for any
+/// depth, and can't restrict a pattern to directories.
+fn docker(p: &Pattern) -> Line {
+    let body = match go_glob(p.body) {
+        Ok(body) => body,
+        Err(why) => return Line::Unsupported(why),
+    };
+    let body = if p.anchored || body.starts_with("**/") {
+        body
+    } else {
+        format!("**/{body}")
+    };
+    let out = format!("{}{body}", p.bang());
+    if p.dir_only {
+        Line::Approx(out, "also matches files, not just directories")
+    } else {
+        Line::Exact(out)
+    }
+}
+
+/// Helm matches like gitignore but with `filepath.Match`, so no `**`.
+fn helm(p: &Pattern) -> Line {
+    if p.body.starts_with("\\!") {
+        return Line::Unsupported("a leading '\\!' isn't supported");
+    }
+    let body = match go_glob(p.body) {
+        Ok(body) => body,
+        Err(why) => return Line::Unsupported(why),
+    };
+    let bang = p.bang();
+    let slash = if p.dir_only { "/" } else { "" };
+
+    if !body.contains("**") {
+        let root = if p.anchored && !body.contains('/') {
+            "/"
+        } else {
+            ""
+        };
+        return Line::Exact(format!("{bang}{root}{body}{slash}"));
+    }
+    // `**/name` is any name at any depth, and `dir/**` everything in dir
+    if let Some(name) = body.strip_prefix("**/")
+        && !name.contains('/')
+        && !name.contains("**")
+    {
+        return Line::Exact(format!("{bang}{name}{slash}"));
+    }
+    if let Some(dir) = body.strip_suffix("/**")
+        && !dir.contains("**")
+    {
+        return Line::Approx(
+            format!("{bang}/{dir}/"),
+            "matches the directory itself as well as its contents",
+        );
+    }
+    Line::Unsupported("'**' isn't supported")
+}
+
+/// Mercurial globs match at any depth unless prefixed with `rootglob:`, and
+/// have no negation or directory-only patterns.
+fn hg(p: &Pattern) -> Line {
+    if p.negated {
+        return Line::Unsupported("negation isn't supported");
+    }
+    let body = p.body.strip_prefix("**/").unwrap_or(p.body);
+    let out = if p.anchored && !p.body.starts_with("**/") {
+        format!("rootglob:{body}")
+    } else {
+        body.to_string()
+    };
+    if p.dir_only {
+        Line::Approx(out, "also matches files, not just directories")
+    } else {
+        Line::Exact(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(template: &str, format: Format) -> Vec<String> {
+        translate(template, format)
+            .content
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(Format::parse(".dockerignore"), Ok(Format::Dockerignore));
+        assert_eq!(Format::parse("HGIGNORE"), Ok(Format::Hgignore));
+        assert!(
+            Format::parse("svnignore")
+                .unwrap_err()
+                .contains("helmignore")
+        );
+        for format in FORMATS {
+            assert_eq!(Format::parse(format.file_name()), Ok(*format));
+        }
+    }
+
+    #[test]
+    fn test_format_for_path() {
+        assert_eq!(
+            Format::for_path(Path::new("app/.dockerignore")),
+            Format::Dockerignore
+        );
+        assert_eq!(
+            Format::for_path(Path::new(".git/info/exclude")),
+            Format::Gitignore
+        );
+        assert_eq!(Format::for_path(Path::new("-")), Format::Gitignore);
+    }
+
+    #[test]
+    fn test_gitignore_syntax_formats_are_verbatim() {
+        let template = "# Logs\n*.log\n!keep.log\nbuild/\n";
+        for format in [
+            Format::Npmignore,
+            Format::Prettierignore,
+            Format::Gcloudignore,
+        ] {
+            assert_eq!(
+                translate(template, format),
+                Translated {
+                    content: template.to_string(),
+                    notes: 0
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_docker() {
+        let out = translate(
+            "# Logs\n*.log\n/dist\nsrc/*.o\n!keep.log\n**/tmp\n*.py[!c]\nbuild/\n[[:digit:]]x\n",
+            Format::Dockerignore,
+        );
+        assert_eq!(
+            out.content,
+            "# Logs\n**/*.log\ndist\nsrc/*.o\n!**/keep.log\n**/tmp\n**/*.py[^c]\n\
+             # gig: approximated \"build/\": also matches files, not just directories\n**/build\n\
+             # gig: left out \"[[:digit:]]x\": named character classes aren't supported\n"
+        );
+        assert_eq!(out.notes, 2);
+    }
+
+    #[test]
+    fn test_helm() {
+        assert_eq!(
+            lines(
+                "*.log\n/dist\nbuild/\n**/tmp/\nsrc/**\na/**/b\n",
+                Format::Helmignore
+            ),
+            vec![
+                "*.log",
+                "/dist",
+                "build/",
+                "tmp/",
+                "# gig: approximated \"src/**\": matches the directory itself as well as its contents",
+                "/src/",
+                "# gig: left out \"a/**/b\": '**' isn't supported",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hg() {
+        let out = translate(
+            "*.log\n/dist\nsrc/*.o\n**/tmp\n!keep.log\nfoo\\ \n",
+            Format::Hgignore,
+        );
+        assert_eq!(
+            out.content,
+            "*.log\nrootglob:dist\nrootglob:src/*.o\ntmp\n\
+             # gig: left out \"!keep.log\": negation isn't supported\n\
+             # gig: left out \"foo\\ \": trailing spaces are always trimmed\n"
+        );
+        assert_eq!(out.notes, 2);
+    }
+
+    #[test]
+    fn test_add_preamble() {
+        let header = format!("{}0.1 from x", blocks::HEADER_PREFIX);
+        assert_eq!(
+            add_preamble("*.log\n", Format::Hgignore),
+            "syntax: glob\n*.log\n"
+        );
+        assert_eq!(
+            add_preamble(&format!("{header}\n*.log\n"), Format::Hgignore),
+            format!("{header}\nsyntax: glob\n*.log\n")
+        );
+        assert_eq!(
+            add_preamble("syntax: glob\n*.log\n", Format::Hgignore),
+            "syntax: glob\n*.log\n"
+        );
+        assert_eq!(add_preamble("*.log\n", Format::Gitignore), "*.log\n");
+    }
+}
@@ -0,0 +1,166 @@
+//! Project markers for `gig --detect`: which templates the files in a
+//! directory imply.
+
+use std::fs;
+use std::path::Path;
+
+/// Something in a project directory that suggests a template.
+#[derive(Debug, Clone, Copy)]
+pub enum Marker {
+    /// A file at this path relative to the directory.
+    File(&'static str),
+    /// A directory at this path relative to the directory.
+    Dir(&'static str),
+    /// Any top-level file with this extension.
+    Extension(&'static str),
+}
+
+/// Marker files and the template each one implies, checked in order.
+///
+/// Templates are looked up like names on the command line, so a bare name
+/// that resolves to a unique nested template (e.g. `jetbrains`) works too.
+pub const MARKERS: &[(Marker, &str)] = &[
+    (Marker::File("Cargo.toml"), "rust"),
+    (Marker::File("go.mod"), "go"),
+    (Marker::File("package.json"), "node"),
+    (Marker::File("pyproject.toml"), "python"),
+    (Marker::File("requirements.txt"), "python"),
+    (Marker::File("setup.py"), "python"),
+    (Marker::File("Pipfile"), "python"),
+    (Marker::File("Gemfile"), "ruby"),
+    (Marker::File("composer.json"), "composer"),
+    (Marker::File("pom.xml"), "maven"),
+    (Marker::File("build.gradle"), "gradle"),
+    (Marker::File("build.gradle.kts"), "gradle"),
+    (Marker::File("CMakeLists.txt"), "cmake"),
+    (Marker::File("mix.exs"), "elixir"),
+    (Marker::File("pubspec.yaml"), "dart"),
+    (Marker::File("Package.swift"), "swift"),
+    (Marker::File("stack.yaml"), "haskell"),
+    (Marker::File("project.godot"), "godot"),
+    (Marker::File("ProjectSettings/ProjectVersion.txt"), "unity"),
+    (Marker::File("hugo.toml"), "hugo"),
+    (Marker::Extension("tf"), "terraform"),
+    (Marker::Extension("csproj"), "visualstudio"),
+    (Marker::Extension("java"), "java"),
+    (Marker::Extension("c"), "c"),
+    (Marker::Dir(".idea"), "global.jetbrains"),
+    (Marker::Dir(".vscode"), "global.visualstudiocode"),
+];
+
+/// A template suggested by a marker found in the project directory.
+#[derive(Debug, PartialEq)]
+pub struct Detection {
+    pub template: &'static str,
+    /// The file or directory that triggered the detection.
+    pub found: String,
+}
+
+/// Scan `dir` for entries in `markers`, returning each implied template once,
+/// in table order, with the first marker that matched it.
+pub fn detect(dir: &Path, markers: &[(Marker, &'static str)]) -> Result<Vec<Detection>, String> {
+    let entries: Vec<String> = fs::read_dir(dir)
+        .map_err(|e| format!("failed to read {}: {e}", dir.display()))?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+
+    let mut detections: Vec<Detection> = Vec::new();
+    for &(marker, template) in markers {
+        if detections.iter().any(|d| d.template == template) {
+            continue;
+        }
+
+        let found = match marker {
+            Marker::File(name) => dir.join(name).is_file().then(|| name.to_string()),
+            Marker::Dir(name) => dir.join(name).is_dir().then(|| format!("{name}/")),
+            Marker::Extension(ext) => {
+                let mut names: Vec<&String> = entries
+                    .iter()
+                    .filter(|n| {
+                        Path::new(n).extension().is_some_and(|e| e == ext) && dir.join(n).is_file()
+                    })
+                    .collect();
+                names.sort_unstable();
+                names.first().map(|n| n.to_string())
+            }
+        };
+
+        if let Some(found) = found {
+            detections.push(Detection { template, found });
+        }
+    }
+
+    Ok(detections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn unique_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gig_detect_{}_{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_detect_files_dirs_and_extensions() {
+        let dir = unique_dir("mixed");
+        fs::write(dir.join("Cargo.toml"), "").unwrap();
+        fs::write(dir.join("main.tf"), "").unwrap();
+        fs::create_dir_all(dir.join(".idea")).unwrap();
+
+        let result = detect(&dir, MARKERS).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Detection {
+                    template: "rust",
+                    found: "Cargo.toml".to_string()
+                },
+                Detection {
+                    template: "terraform",
+                    found: "main.tf".to_string()
+                },
+                Detection {
+                    template: "global.jetbrains",
+                    found: ".idea/".to_string()
+                },
+            ]
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_detect_template_reported_once() {
+        let dir = unique_dir("once");
+        fs::write(dir.join("pyproject.toml"), "").unwrap();
+        fs::write(dir.join("requirements.txt"), "").unwrap();
+
+        let result = detect(&dir, MARKERS).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].found, "pyproject.toml");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_detect_marker_kind_must_match() {
+        let dir = unique_dir("kind");
+        // A directory named like a marker file is not a match
+        fs::create_dir_all(dir.join("go.mod")).unwrap();
+        fs::write(dir.join(".vscode"), "").unwrap();
+
+        assert!(detect(&dir, MARKERS).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_detect_missing_dir() {
+        let result = detect(Path::new("/nonexistent/gig/dir"), MARKERS);
+        assert!(result.unwrap_err().contains("failed to read"));
+    }
+}
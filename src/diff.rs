@@ -0,0 +1,200 @@
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Render a unified diff between `old` and `new`, or an empty string when they match.
+///
+/// `old_label` and `new_label` go on the `---` and `+++` header lines.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let ops = edit_script(&a, &b);
+
+    if ops.iter().all(|&op| op == Op::Equal) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {old_label}\n+++ {new_label}\n");
+
+    // Position in a and b of each op, so hunks can be sliced out of the script
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for &op in &ops {
+        positions.push((i, j));
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+
+    let mut k = 0;
+    while k < ops.len() {
+        if ops[k] == Op::Equal {
+            k += 1;
+            continue;
+        }
+
+        // Extend the hunk until more than 2 * CONTEXT unchanged lines follow a change
+        let start = k.saturating_sub(CONTEXT);
+        let mut end = k;
+        let mut trailing = 0;
+        while end < ops.len() {
+            if ops[end] == Op::Equal {
+                if trailing == 2 * CONTEXT {
+                    break;
+                }
+                trailing += 1;
+            } else {
+                trailing = 0;
+            }
+            end += 1;
+        }
+        let end = end - trailing.saturating_sub(CONTEXT);
+
+        let (old_start, new_start) = positions[start];
+        let old_len = ops[start..end]
+            .iter()
+            .filter(|&&op| op != Op::Insert)
+            .count();
+        let new_len = ops[start..end]
+            .iter()
+            .filter(|&&op| op != Op::Delete)
+            .count();
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+
+        for (&op, &(i, j)) in ops[start..end].iter().zip(&positions[start..end]) {
+            let (prefix, line) = match op {
+                Op::Equal => (' ', a[i]),
+                Op::Delete => ('-', a[i]),
+                Op::Insert => ('+', b[j]),
+            };
+            output.push(prefix);
+            output.push_str(line);
+            output.push('\n');
+        }
+
+        k = end;
+    }
+
+    output
+}
+
+/// Format a hunk range the way `diff -u` does: `start,len` with 1-based start,
+/// where an empty range points at the line before it.
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{len}", start + 1),
+    }
+}
+
+/// Compute a line-level edit script from the longest common subsequence.
+fn edit_script(a: &[&str], b: &[&str]) -> Vec<Op> {
+    // Common prefix and suffix need no table
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    // lcs[i][j] = LCS length of a_mid[i..] and b_mid[j..]
+    let (n, m) = (a_mid.len(), b_mid.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a_mid[i] == b_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = vec![Op::Equal; prefix];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a_mid[i] == b_mid[j] {
+            ops.push(Op::Equal);
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_identical() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), "");
+    }
+
+    #[test]
+    fn test_unified_diff_insertion() {
+        let diff = unified_diff("a\nb\n", "a\nx\nb\n", "old", "new");
+        assert_eq!(diff, "--- old\n+++ new\n@@ -1,2 +1,3 @@\n a\n+x\n b\n");
+    }
+
+    #[test]
+    fn test_unified_diff_from_empty() {
+        let diff = unified_diff("", "a\nb\n", "/dev/null", "new");
+        assert_eq!(diff, "--- /dev/null\n+++ new\n@@ -0,0 +1,2 @@\n+a\n+b\n");
+    }
+
+    #[test]
+    fn test_unified_diff_replacement() {
+        let diff = unified_diff("a\nb\nc\n", "a\nB\nc\n", "old", "new");
+        assert_eq!(diff, "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+    }
+
+    #[test]
+    fn test_unified_diff_separate_hunks() {
+        let old: String = (1..=20).map(|n| format!("{n}\n")).collect();
+        let new: String = (1..=20)
+            .map(|n| match n {
+                2 => "two\n".to_string(),
+                19 => "nineteen\n".to_string(),
+                _ => format!("{n}\n"),
+            })
+            .collect();
+        let diff = unified_diff(&old, &new, "old", "new");
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n"));
+        assert!(diff.contains("@@ -16,5 +16,5 @@\n 16\n 17\n 18\n-19\n+nineteen\n 20\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_close_changes_share_hunk() {
+        let old = "1\n2\n3\n4\n5\n6\n";
+        let new = "x\n2\n3\n4\n5\ny\n";
+        let diff = unified_diff(old, new, "old", "new");
+        assert_eq!(diff.matches("@@ -").count(), 1);
+    }
+}
@@ -0,0 +1,199 @@
+//! Canonical layout for an existing ignore file, for `gig fmt`.
+//!
+//! Only hand-written lines are rewritten; gig's managed blocks are kept as
+//! generated so `gig check` still matches them.
+
+use crate::blocks::{self, Segment};
+use crate::merge::{Dedupe, LineKind, Seen};
+
+/// Rewrite `content` in canonical form: LF endings, one blank line at most
+/// between sections and none at either end, trailing whitespace trimmed
+/// (escaped spaces kept), and hand-written patterns that repeat an earlier
+/// hand-written one dropped. A line repeating a block's pattern stays, since
+/// it still applies once the block is removed. With `sort`, runs of patterns
+/// between comments, blank lines and changes of polarity are sorted.
+pub fn format(content: &str, sort: bool) -> Result<String, String> {
+    let segments = blocks::parse_segments(content)?;
+    let last = segments.len().saturating_sub(1);
+
+    let mut seen = Seen::new(Dedupe::Exact);
+    // Whether each pattern in `seen` is hand-written, by position
+    let mut hand_written: Vec<bool> = Vec::new();
+    let mut formatted = Vec::with_capacity(segments.len());
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Text(lines) => {
+                let mut lines = format_lines(lines, &mut seen, &mut hand_written);
+                if sort {
+                    sort_runs(&mut lines);
+                }
+                if i == 0 {
+                    let leading = lines.iter().take_while(|l| l.is_empty()).count();
+                    lines.drain(..leading);
+                }
+                if i == last {
+                    while lines.last().is_some_and(String::is_empty) {
+                        lines.pop();
+                    }
+                }
+                if !lines.is_empty() {
+                    formatted.push(Segment::Text(lines));
+                }
+            }
+            Segment::Block { name, lines } => {
+                for pattern in lines.iter().filter_map(|l| blocks::pattern_of(l)) {
+                    seen.insert(pattern);
+                    hand_written.push(false);
+                }
+                formatted.push(Segment::Block {
+                    name: name.clone(),
+                    lines: lines.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(blocks::render_segments(&formatted))
+}
+
+/// Trim, dedupe and collapse blank runs in hand-written lines. A repeat is
+/// only dropped when the closest copy is hand-written and no line of
+/// opposite polarity that might match the same paths sits between the copies
+/// (see `Seen::redundant_with`).
+fn format_lines<'a>(
+    lines: &'a [String],
+    seen: &mut Seen<'a>,
+    hand_written: &mut Vec<bool>,
+) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    for line in lines {
+        match LineKind::of(line) {
+            LineKind::Blank => {
+                if out.last().is_none_or(|l| !l.is_empty()) {
+                    out.push(String::new());
+                }
+            }
+            LineKind::Comment => out.push(line.trim_end().to_string()),
+            LineKind::Pattern(pattern) => {
+                if seen
+                    .redundant_with(pattern)
+                    .is_none_or(|i| !hand_written[i])
+                {
+                    seen.insert(pattern);
+                    hand_written.push(true);
+                    out.push(pattern.to_string());
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Sort each run of consecutive patterns of the same polarity.
+///
+/// Git lets the last matching line win, so only the order of a pattern
+/// relative to `!` lines matters; comments and blank lines also stay put.
+fn sort_runs(lines: &mut [String]) {
+    let mut start = 0;
+    while start < lines.len() {
+        let negated = polarity(&lines[start]);
+        let len = lines[start..]
+            .iter()
+            .take_while(|l| negated.is_some() && polarity(l) == negated)
+            .count()
+            .max(1);
+        lines[start..start + len].sort();
+        start += len;
+    }
+}
+
+/// Whether a line is a negated pattern, or None if it isn't a pattern.
+fn polarity(line: &str) -> Option<bool> {
+    match LineKind::of(line) {
+        LineKind::Pattern(pattern) => Some(pattern.starts_with('!')),
+        LineKind::Blank | LineKind::Comment => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_layout() {
+        let content = "\n\n# Build\r\nbuild/  \r\n\r\n\r\n\r\n*.log\t \nfoo\\ \n# note   \n\n\n";
+        assert_eq!(
+            format(content, false).unwrap(),
+            "# Build\nbuild/\n\n*.log\t\nfoo\\ \n# note\n"
+        );
+    }
+
+    #[test]
+    fn test_format_drops_duplicates_unless_negation_between() {
+        let content = "*.log\nbuild/\n*.log\n!keep.log\n*.log\n";
+        assert_eq!(
+            format(content, false).unwrap(),
+            "*.log\nbuild/\n!keep.log\n*.log\n"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_repeat_after_wildcard_negation() {
+        // The last foo* re-ignores foobar, which !*bar re-included
+        let content = "foo*\n!*bar\nfoo*\n";
+        assert_eq!(format(content, false).unwrap(), content);
+        assert_eq!(format(content, true).unwrap(), content);
+    }
+
+    #[test]
+    fn test_format_sort_keeps_negation_boundaries() {
+        let content =
+            "# Logs\nz.log\na.log\n!keep.log\n!b.log\ny.log\nb.log\n\n# Dirs\ndist/\nbuild/\n";
+        assert_eq!(
+            format(content, true).unwrap(),
+            "# Logs\na.log\nz.log\n!b.log\n!keep.log\nb.log\ny.log\n\n# Dirs\nbuild/\ndist/\n"
+        );
+    }
+
+    #[test]
+    fn test_format_leaves_blocks_alone() {
+        let content = "mine/\n*.pyc\n\n\n# >>> gig: python\n*.pyc\n\n\n__pycache__/\n# <<< gig: python\n*.pyc\n\n";
+        assert_eq!(
+            format(content, true).unwrap(),
+            "*.pyc\nmine/\n\n# >>> gig: python\n*.pyc\n\n\n__pycache__/\n# <<< gig: python\n*.pyc\n"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_hand_written_repeat_of_block_line() {
+        // Still needed once `gig remove python` drops the block
+        let content = "# >>> gig: python\n*.pyc\n# <<< gig: python\n*.pyc\n";
+        assert_eq!(format(content, false).unwrap(), content);
+        // Repeats among hand-written lines still go
+        let content = "*.pyc\n# >>> gig: python\n*.pyc\n# <<< gig: python\n*.pyc\n*.pyc\n";
+        assert_eq!(
+            format(content, false).unwrap(),
+            "*.pyc\n# >>> gig: python\n*.pyc\n# <<< gig: python\n*.pyc\n"
+        );
+    }
+
+    #[test]
+    fn test_format_indented_hash_is_a_pattern() {
+        // Git reads "  # x" as a pattern, so it is kept and sorted like one
+        let content = "b\n  # x\n  # x\n";
+        assert_eq!(format(content, false).unwrap(), "b\n  # x\n");
+        assert_eq!(format(content, true).unwrap(), "  # x\nb\n");
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let content = "b\na\n\n\n!c\n  # x\n";
+        let once = format(content, true).unwrap();
+        assert_eq!(format(&once, true).unwrap(), once);
+    }
+
+    #[test]
+    fn test_format_invalid_blocks() {
+        assert!(format("# >>> gig: go\n*.exe\n", false).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Running git for information about the work tree.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config;
+
+/// Whether `dir` is inside a git work tree.
+pub fn is_work_tree(dir: &Path) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|o| o.status.success() && o.stdout.starts_with(b"true"))
+}
+
+/// Files tracked by git under `dir`, relative to `dir`.
+pub fn tracked_files(dir: &Path) -> Result<Vec<String>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "-z"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git ls-files failed: {}", stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The repository's `info/exclude` file for `dir`, wherever its git dir is:
+/// `$GIT_DIR`, a `.git` file pointing elsewhere, or the common dir shared by
+/// linked worktrees.
+pub fn exclude_file(dir: &Path) -> Result<PathBuf, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--git-path", "info/exclude"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git rev-parse failed: {}", stderr.trim()));
+    }
+
+    // Relative to `dir`, unless git gave an absolute path
+    let path = String::from_utf8_lossy(&output.stdout);
+    Ok(dir.join(path.trim_end_matches('\n')))
+}
+
+/// The personal excludes file: `core.excludesFile`, or git's default of
+/// `$XDG_CONFIG_HOME/git/ignore` when it isn't set.
+pub fn global_excludes_file() -> Result<PathBuf, String> {
+    let output = Command::new("git")
+        .args(["config", "--path", "--get", "core.excludesFile"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    // Exit code 1 means the key isn't set
+    match output.status.code() {
+        Some(0) => {
+            let path = String::from_utf8_lossy(&output.stdout);
+            Ok(PathBuf::from(path.trim_end_matches('\n')))
+        }
+        Some(1) => config::config_home()
+            .map(|dir| dir.join("git").join("ignore"))
+            .ok_or_else(|| {
+                "can't find the global excludes file: set core.excludesFile or HOME".to_string()
+            }),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(format!("git config failed: {}", stderr.trim()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_tracked_files() {
+        let dir = std::env::temp_dir().join(format!("gig_git_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/tracked.txt"), "").unwrap();
+        fs::write(dir.join("untracked.txt"), "").unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "--quiet"]);
+        git(&["add", "sub/tracked.txt"]);
+
+        assert!(is_work_tree(&dir.join("sub")));
+        assert_eq!(tracked_files(&dir).unwrap(), vec!["sub/tracked.txt"]);
+        assert_eq!(
+            tracked_files(&dir.join("sub")).unwrap(),
+            vec!["tracked.txt"]
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_exclude_file_in_worktree() {
+        let dir = std::env::temp_dir().join(format!("gig_git_exclude_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let main = dir.join("main");
+        fs::create_dir_all(&main).unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&main)
+                .args(["-c", "user.name=gig", "-c", "user.email=gig@example.com"])
+                .args(args)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "--quiet"]);
+        git(&["commit", "--quiet", "--allow-empty", "-m", "init"]);
+        git(&["worktree", "add", "--quiet", "../linked"]);
+
+        let expected = fs::canonicalize(main.join(".git/info")).unwrap();
+        for dir in [&main, &dir.join("linked")] {
+            let file = exclude_file(dir).unwrap();
+            assert_eq!(file.file_name().unwrap(), "exclude");
+            assert_eq!(fs::canonicalize(file.parent().unwrap()).unwrap(), expected);
+        }
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_tracked_files_outside_work_tree() {
+        assert!(!is_work_tree(Path::new("/nonexistent/gig")));
+        let err = tracked_files(Path::new("/nonexistent/gig")).unwrap_err();
+        assert!(err.contains("git ls-files failed"), "got: {err}");
+        let err = exclude_file(Path::new("/nonexistent/gig")).unwrap_err();
+        assert!(err.contains("git rev-parse failed"), "got: {err}");
+    }
+}
@@ -0,0 +1,230 @@
+//! Output for `--json`: a small JSON value type, and the stable codes
+//! errors are reported under.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Integer(usize),
+    String(String),
+    Array(Vec<Value>),
+    /// Keys are written in the order given.
+    Object(Vec<(&'static str, Value)>),
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Boolean(b)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Value {
+        Value::Integer(n)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Value {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Value {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Compact JSON, on one line.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Boolean(b) => write!(f, "{b}"),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// What kind of failure an error is. The codes are part of the `--json`
+/// interface, so existing ones must not change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    /// Bad or conflicting arguments.
+    Usage,
+    /// The config file or `.gig.toml` can't be read, or a preset or alias in
+    /// the config is broken.
+    Config,
+    /// A name matches no template, or more than one.
+    UnknownTemplate,
+    /// The output file exists and `--append` wasn't given.
+    FileExists,
+    /// An existing file has malformed gig blocks.
+    InvalidFile,
+    /// `--strict` refused to ignore tracked files.
+    TrackedFiles,
+    /// Reading or writing a file failed.
+    Io,
+    /// A subcommand found what it checks for: files out of date, lint
+    /// errors, no matches.
+    Failed,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Usage => "usage",
+            ErrorCode::Config => "config",
+            ErrorCode::UnknownTemplate => "unknown-template",
+            ErrorCode::FileExists => "file-exists",
+            ErrorCode::InvalidFile => "invalid-file",
+            ErrorCode::TrackedFiles => "tracked-files",
+            ErrorCode::Io => "io",
+            ErrorCode::Failed => "failed",
+        }
+    }
+}
+
+/// A failure and the code it's reported under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Error {
+        Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Prefix the message with what failed, e.g. a file name.
+    pub fn context(self, what: impl fmt::Display) -> Error {
+        Error {
+            message: format!("{what}: {}", self.message),
+            ..self
+        }
+    }
+}
+
+/// Give a plain error message its code.
+pub trait WithCode<T> {
+    fn code(self, code: ErrorCode) -> Result<T, Error>;
+}
+
+impl<T, E: Into<String>> WithCode<T> for Result<T, E> {
+    fn code(self, code: ErrorCode) -> Result<T, Error> {
+        self.map_err(|e| Error::new(code, e))
+    }
+}
+
+/// `{"error": {"code": ..., "message": ...}}`
+pub fn error(code: ErrorCode, message: &str) -> Value {
+    Value::Object(vec![(
+        "error",
+        Value::Object(vec![
+            ("code", code.as_str().into()),
+            ("message", message.into()),
+        ]),
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let value = Value::Object(vec![
+            ("key", "python".into()),
+            ("subcategory", Value::Null),
+            ("local", false.into()),
+            ("lines", 3usize.into()),
+            ("related", vec!["a", "b"].into()),
+            ("empty", Value::Array(Vec::new())),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{"key":"python","subcategory":null,"local":false,"lines":3,"related":["a","b"],"empty":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let value = Value::from("say \"hi\"\\\n\t\u{1}é");
+        assert_eq!(value.to_string(), r#""say \"hi\"\\\n\t\u0001é""#);
+    }
+
+    #[test]
+    fn test_with_code() {
+        let result: Result<(), &str> = Err("no such file");
+        let error = result
+            .code(ErrorCode::Io)
+            .unwrap_err()
+            .context(".gitignore");
+        assert_eq!(error, Error::new(ErrorCode::Io, ".gitignore: no such file"));
+    }
+
+    #[test]
+    fn test_error() {
+        assert_eq!(
+            error(ErrorCode::UnknownTemplate, "no template found").to_string(),
+            r#"{"error":{"code":"unknown-template","message":"no template found"}}"#
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! How the github/gitignore directory layout maps to template keys.
+//!
+//! Shared by `build.rs` (embedded templates) and the runtime index (local
+//! template directories), so both name templates the same way.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const GITIGNORE_SUFFIX: &str = ".gitignore";
+
+/// Compute the destination filename based on the template's scope.
+///
+/// - Top-level: `{name}.gitignore`
+/// - Global/: `global.{name}.gitignore`
+/// - community/{subcategory}/: `community.{subcategory}.{name}.gitignore`
+/// - community/ (direct): `community.{name}.gitignore`
+pub fn compute_dest_name(rel_path: &Path, bare_name: &str) -> String {
+    let components: Vec<&str> = rel_path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .collect();
+
+    if components.is_empty() {
+        // Top-level template
+        format!("{bare_name}{GITIGNORE_SUFFIX}")
+    } else if components[0].eq_ignore_ascii_case("global") {
+        format!("global.{bare_name}{GITIGNORE_SUFFIX}")
+    } else if components[0].eq_ignore_ascii_case("community") {
+        if components.len() > 1 {
+            // community/{subcategory}/{name}.gitignore
+            let subcategory = components[1];
+            format!("community.{subcategory}.{bare_name}{GITIGNORE_SUFFIX}")
+        } else {
+            // community/{name}.gitignore (no subcategory)
+            format!("community.{bare_name}{GITIGNORE_SUFFIX}")
+        }
+    } else {
+        // Unknown subdirectory — treat like top-level with prefix
+        let prefix = components.join(".");
+        format!("{prefix}.{bare_name}{GITIGNORE_SUFFIX}")
+    }
+}
+
+/// A template's path relative to the templates root, `/`-separated as in
+/// github/gitignore, e.g. `community/Golang/Hugo.gitignore`.
+pub fn upstream_path(rel_path: &Path) -> String {
+    let components: Vec<&str> = rel_path
+        .components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .collect();
+    components.join("/")
+}
+
+/// Recursively collect `(path, bare_name)` for every `*.gitignore` file under `dir`.
+///
+/// Hidden directories such as `.git` are skipped.
+pub fn collect_templates(dir: &Path, templates: &mut Vec<(PathBuf, String)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            if !name.starts_with('.') {
+                collect_templates(&path, templates)?;
+            }
+        } else if let Some(bare) = name.strip_suffix(GITIGNORE_SUFFIX)
+            && !bare.is_empty()
+        {
+            templates.push((path.clone(), bare.to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_dest_name_scopes() {
+        assert_eq!(
+            compute_dest_name(Path::new("Python.gitignore"), "Python"),
+            "Python.gitignore"
+        );
+        assert_eq!(
+            compute_dest_name(Path::new("Global/macOS.gitignore"), "macOS"),
+            "global.macOS.gitignore"
+        );
+        assert_eq!(
+            compute_dest_name(Path::new("community/Golang/Hugo.gitignore"), "Hugo"),
+            "community.Golang.Hugo.gitignore"
+        );
+        assert_eq!(
+            compute_dest_name(Path::new("community/Terraform.gitignore"), "Terraform"),
+            "community.Terraform.gitignore"
+        );
+        assert_eq!(
+            compute_dest_name(Path::new("internal/tools/Bazel.gitignore"), "Bazel"),
+            "internal.tools.Bazel.gitignore"
+        );
+    }
+
+    #[test]
+    fn test_upstream_path() {
+        let rel: PathBuf = ["community", "Golang", "Hugo.gitignore"].iter().collect();
+        assert_eq!(upstream_path(&rel), "community/Golang/Hugo.gitignore");
+        assert_eq!(upstream_path(Path::new("Go.gitignore")), "Go.gitignore");
+    }
+
+    #[test]
+    fn test_collect_templates_recurses_and_skips_hidden() {
+        let dir = std::env::temp_dir().join(format!("gig_layout_{}", std::process::id()));
+        fs::create_dir_all(dir.join("Global")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join("Tool.gitignore"), "").unwrap();
+        fs::write(dir.join("Global/Editor.gitignore"), "").unwrap();
+        fs::write(dir.join(".git/Hidden.gitignore"), "").unwrap();
+        fs::write(dir.join("README.md"), "").unwrap();
+        fs::write(dir.join(".gitignore"), "").unwrap();
+
+        let mut templates = Vec::new();
+        collect_templates(&dir, &mut templates).unwrap();
+        let mut names: Vec<&str> = templates.iter().map(|(_, n)| n.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["Editor", "Tool"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
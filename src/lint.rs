@@ -0,0 +1,452 @@
+//! Problems in an existing ignore file, for `gig lint`.
+//!
+//! Each finding names a line and, where the change can't alter what the file
+//! ignores, carries a fix: dropping a line that repeats or can't match, or
+//! trimming what git trims anyway.
+
+use std::fmt;
+
+use crate::matcher::{self, Matcher};
+use crate::merge::{Dedupe, LineKind, Seen};
+use crate::normalize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// Harmless to git, but cruft or easy to misread.
+    Warning,
+    /// The line doesn't do what it looks like it does.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A safe edit for a finding.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    /// Remove the line.
+    Delete,
+    /// Replace the line with this text.
+    Replace(String),
+    /// Use `\n` line endings throughout the file.
+    LineEndings,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// 1-based line number.
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+impl Finding {
+    fn new(line: usize, severity: Severity, message: String) -> Finding {
+        Finding {
+            line,
+            severity,
+            message,
+            fix: None,
+        }
+    }
+
+    fn with_fix(self, fix: Fix) -> Finding {
+        Finding {
+            fix: Some(fix),
+            ..self
+        }
+    }
+}
+
+/// Check every line of `content`, returning findings in line order.
+pub fn lint(content: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if let Some(i) = content.split('\n').position(|l| l.ends_with('\r')) {
+        let count = content.matches("\r\n").count();
+        findings.push(
+            Finding::new(
+                i + 1,
+                Severity::Warning,
+                format!("CRLF line endings ({count} lines); use LF so every tool reads the same patterns"),
+            )
+            .with_fix(Fix::LineEndings),
+        );
+    }
+
+    let matcher = Matcher::parse(content);
+    let mut seen = Seen::new(Dedupe::Semantic);
+    // Line number of each pattern in `seen`, by position
+    let mut seen_lines: Vec<usize> = Vec::new();
+
+    for (i, raw) in content.lines().enumerate() {
+        let lineno = i + 1;
+        let line = raw.trim_end_matches('\r');
+        let LineKind::Pattern(text) = LineKind::of(line) else {
+            continue;
+        };
+        let mut line_findings = whitespace(lineno, line, text);
+
+        if let Some(reason) = never_matches(text) {
+            line_findings.push(
+                Finding::new(
+                    lineno,
+                    Severity::Error,
+                    format!("{reason}; it can't match anything"),
+                )
+                .with_fix(Fix::Delete),
+            );
+            findings.append(&mut line_findings);
+            continue;
+        }
+        line_findings.extend(
+            regex_syntax(text).map(|message| Finding::new(lineno, Severity::Warning, message)),
+        );
+
+        match seen.covering(text) {
+            Some((j, between)) if between.is_empty() => {
+                let message = repeat_message(seen.order[j], seen_lines[j], text);
+                // Removing the line makes its other findings moot
+                line_findings =
+                    vec![Finding::new(lineno, Severity::Warning, message).with_fix(Fix::Delete)];
+            }
+            covering => {
+                // Past a negation that only might overlap it, the repeat may
+                // still matter, so it is reported but not removed
+                if let Some((j, between)) = covering
+                    && !between
+                        .iter()
+                        .any(|&k| matcher::shares_path(text, seen.order[k]))
+                {
+                    let negation = between[0];
+                    line_findings.push(Finding::new(
+                        lineno,
+                        Severity::Warning,
+                        format!(
+                            "{}, unless \"{}\" on line {} re-includes some of its paths",
+                            repeat_message(seen.order[j], seen_lines[j], text),
+                            seen.order[negation],
+                            seen_lines[negation]
+                        ),
+                    ));
+                }
+                seen.insert(text);
+                seen_lines.push(lineno);
+            }
+        }
+
+        if let Some(parent) = excluded_parent(&matcher, text) {
+            line_findings.push(Finding::new(
+                lineno,
+                Severity::Error,
+                format!(
+                    "negation can never apply: its parent directory {}/ is excluded by \"{}\" on line {}, and git doesn't look inside excluded directories",
+                    parent.0, parent.1.text, parent.1.line
+                ),
+            ));
+        }
+
+        findings.append(&mut line_findings);
+    }
+
+    findings
+}
+
+/// How a line repeats the earlier `covering` line on `line`.
+fn repeat_message(covering: &str, line: usize, text: &str) -> String {
+    if covering == text {
+        format!("duplicate of line {line}")
+    } else {
+        format!("already covered by \"{covering}\" on line {line}")
+    }
+}
+
+/// `content` with every fix in `findings` applied.
+pub fn apply_fixes(content: &str, findings: &[Finding]) -> String {
+    let lf = findings.iter().any(|f| f.fix == Some(Fix::LineEndings));
+    let mut out = String::with_capacity(content.len());
+
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let lineno = i + 1;
+        let body = line.trim_end_matches('\n').trim_end_matches('\r');
+        let ending = if lf && line.ends_with('\n') {
+            "\n"
+        } else {
+            &line[body.len()..]
+        };
+
+        let fixes: Vec<&Fix> = findings
+            .iter()
+            .filter(|f| f.line == lineno)
+            .filter_map(|f| f.fix.as_ref())
+            .collect();
+        if fixes.contains(&&Fix::Delete) {
+            continue;
+        }
+        let text = fixes.iter().find_map(|fix| match fix {
+            Fix::Replace(text) => Some(text.as_str()),
+            _ => None,
+        });
+        out.push_str(text.unwrap_or(body));
+        out.push_str(ending);
+    }
+
+    out
+}
+
+/// Leading and trailing whitespace that git treats differently than it looks.
+fn whitespace(lineno: usize, line: &str, text: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if line.starts_with([' ', '\t']) {
+        let message = if line.trim_start().starts_with('#') {
+            "leading whitespace makes this a pattern, not a comment"
+        } else {
+            "leading whitespace is part of the pattern"
+        };
+        findings.push(Finding::new(lineno, Severity::Warning, message.to_string()));
+    }
+    if text.len() < line.len() {
+        findings.push(
+            Finding::new(
+                lineno,
+                Severity::Warning,
+                "trailing spaces are ignored by git".to_string(),
+            )
+            .with_fix(Fix::Replace(text.to_string())),
+        );
+    } else if text.ends_with('\t') {
+        findings.push(Finding::new(
+            lineno,
+            Severity::Warning,
+            "trailing tab is part of the pattern (git only trims spaces)".to_string(),
+        ));
+    }
+    findings
+}
+
+/// Why a pattern line can't match any path, if it can't.
+fn never_matches(text: &str) -> Option<&'static str> {
+    let body = text.strip_prefix('!').unwrap_or(text);
+    if matcher::Pattern::parse(text, 0).is_none() {
+        return Some("pattern is empty");
+    }
+    let backslashes = body.len() - body.trim_end_matches('\\').len();
+    if backslashes % 2 == 1 {
+        return Some("trailing backslash escapes nothing");
+    }
+    if has_unclosed_class(body) {
+        return Some("'[' is never closed");
+    }
+
+    let path = body.trim_start_matches('/').trim_end_matches('/');
+    if path.contains("//") {
+        return Some("paths never contain an empty component ('//')");
+    }
+    if path
+        .split('/')
+        .any(|segment| segment == "." || segment == "..")
+    {
+        return Some("paths are matched without '.' or '..' components");
+    }
+    None
+}
+
+/// Whether a bracket expression in `glob` has no closing `]`, which git
+/// treats as a pattern that never matches.
+fn has_unclosed_class(glob: &str) -> bool {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '[' => {
+                let mut j = i + 1;
+                if matches!(chars.get(j), Some('!' | '^')) {
+                    j += 1;
+                }
+                // A ']' right after the opening is a literal member
+                if chars.get(j) == Some(&']') {
+                    j += 1;
+                }
+                match chars[j.min(chars.len())..].iter().position(|&c| c == ']') {
+                    Some(end) => i = j + end + 1,
+                    None => return true,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+/// Regular-expression syntax that gitignore globs read literally.
+fn regex_syntax(text: &str) -> Option<String> {
+    let body = text.strip_prefix('!').unwrap_or(text);
+    let message = if body.starts_with('^') {
+        "'^' is literal here; anchor a pattern with a leading '/' instead"
+    } else if body.ends_with('$') && !body.ends_with("\\$") {
+        "'$' is literal here; patterns already match whole names"
+    } else if ["\\.", "\\d", "\\w", "\\s", ".+"]
+        .iter()
+        .any(|s| body.contains(s))
+    {
+        "looks like a regular expression; gitignore uses globs ('*', '?', '[...]')"
+    } else if body.contains('(') && body.contains('|') {
+        "'(a|b)' alternation isn't supported; list each pattern on its own line"
+    } else if body.contains('{') && body.contains(',') && body.contains('}') {
+        "'{a,b}' braces aren't expanded; list each pattern on its own line"
+    } else {
+        return None;
+    };
+    Some(message.to_string())
+}
+
+/// For a negated pattern naming a path under fixed directories, the first of
+/// those directories that the file excludes, with the deciding pattern.
+fn excluded_parent<'a>(matcher: &'a Matcher, text: &str) -> Option<(String, &'a matcher::Pattern)> {
+    let normal = normalize::normalize(text)?;
+    if !normal.negated || !normal.anchored {
+        return None;
+    }
+
+    let segments: Vec<&str> = normal.body.split('/').collect();
+    let parents = if normal.contents {
+        segments.len()
+    } else {
+        segments.len() - 1
+    };
+    let mut parent = String::new();
+    for segment in &segments[..parents] {
+        if segment.contains(['*', '?', '[', '\\']) {
+            return None;
+        }
+        if !parent.is_empty() {
+            parent.push('/');
+        }
+        parent.push_str(segment);
+        if let Some(pattern) = matcher.last_match(&parent, true)
+            && !pattern.negated
+        {
+            return Some((parent, pattern));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(content: &str) -> Vec<(usize, Severity, String)> {
+        lint(content)
+            .into_iter()
+            .map(|f| (f.line, f.severity, f.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean_file() {
+        assert!(lint("# build output\ntarget/\n*.log\n!keep.log\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_lint_duplicates_and_shadowed() {
+        let found = messages("*.log\nbuild/\n*.log\n/debug.log\nbuild/**\n");
+        assert_eq!(
+            found,
+            vec![
+                (3, Severity::Warning, "duplicate of line 1".to_string()),
+                (
+                    4,
+                    Severity::Warning,
+                    "already covered by \"*.log\" on line 1".to_string()
+                ),
+                (
+                    5,
+                    Severity::Warning,
+                    "already covered by \"build/\" on line 2".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_repeat_after_negation_is_not_duplicate() {
+        assert!(lint("*.log\n!keep.log\n*.log\n").is_empty());
+    }
+
+    #[test]
+    fn test_lint_repeat_after_possible_negation_has_no_fix() {
+        let content = "foo*\n!*bar\nfoo*\n";
+        let found = lint(content);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].line, 3);
+        assert_eq!(
+            found[0].message,
+            "duplicate of line 1, unless \"!*bar\" on line 2 re-includes some of its paths"
+        );
+        assert_eq!(found[0].fix, None);
+        assert_eq!(apply_fixes(content, &found), content);
+    }
+
+    #[test]
+    fn test_lint_negation_under_excluded_dir() {
+        let found = lint("build/\n!build/keep.txt\nlogs/*\n!logs/keep.log\n");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].line, 2);
+        assert_eq!(found[0].severity, Severity::Error);
+        assert!(
+            found[0].message.contains("build/ is excluded"),
+            "{:?}",
+            found[0]
+        );
+    }
+
+    #[test]
+    fn test_lint_whitespace() {
+        let found = lint("*.log  \nfoo\\ \n  *.tmp\n # note\n*.bak\t\n");
+        let lines: Vec<usize> = found.iter().map(|f| f.line).collect();
+        assert_eq!(lines, vec![1, 3, 4, 5]);
+        assert_eq!(found[0].fix, Some(Fix::Replace("*.log".to_string())));
+        assert!(found[2].message.contains("not a comment"));
+        assert!(found[3].message.contains("trailing tab"));
+    }
+
+    #[test]
+    fn test_lint_never_matches() {
+        for line in ["/", "foo\\", "[abc", "a//b", "./build", "src/../lib"] {
+            let found = lint(&format!("{line}\n"));
+            assert_eq!(found.len(), 1, "{line}: {found:?}");
+            assert_eq!(found[0].severity, Severity::Error);
+            assert_eq!(found[0].fix, Some(Fix::Delete));
+        }
+        assert!(lint("[]]\n[!]a]\n[[:alpha:]]\n").is_empty());
+    }
+
+    #[test]
+    fn test_lint_regex_syntax() {
+        for line in ["^build", "*.log$", ".*\\.log", "*.{js,ts}", "(foo|bar)"] {
+            let found = lint(&format!("{line}\n"));
+            assert_eq!(found.len(), 1, "{line}: {found:?}");
+            assert_eq!(found[0].fix, None);
+        }
+        assert!(lint(".*\n*.py[cod]\n").is_empty());
+    }
+
+    #[test]
+    fn test_apply_fixes() {
+        let content = "*.log\r\n*.tmp  \r\n*.log\r\n/\r\n!keep.log\r\n";
+        let found = lint(content);
+        assert_eq!(found[0].fix, Some(Fix::LineEndings));
+        let fixed = apply_fixes(content, &found);
+        assert_eq!(fixed, "*.log\n*.tmp\n!keep.log\n");
+        assert!(lint(&fixed).is_empty());
+    }
+}
@@ -0,0 +1,383 @@
+//! The project record (`.gig.toml`) of which templates each generated file
+//! was built from, used by `gig sync` and `gig check`.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use crate::convert::Format;
+use crate::merge::Dedupe;
+use crate::toml;
+
+/// File name of the record, kept in the directory gig first runs in
+/// (usually the repository root) and found from its subdirectories.
+pub const LOCKFILE: &str = ".gig.toml";
+
+const PREAMBLE: &str = "# Templates used for each generated file. Run 'gig sync' to regenerate\n\
+                        # the files from this record, or 'gig check' to verify them.\n";
+
+/// How one ignore file was generated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Path of the generated file, relative to the record's directory.
+    pub path: String,
+    /// Template keys, in the order their blocks appear in the file.
+    pub templates: Vec<String>,
+    /// The template revision the file was last generated from.
+    pub revision: Option<String>,
+    /// Whether the file starts with a provenance header.
+    pub header: bool,
+    pub settings: Settings,
+}
+
+/// Generation options that change a file's content, so `gig sync` must
+/// repeat them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Settings {
+    /// How repeated patterns were deduplicated.
+    pub dedupe: Dedupe,
+    /// The ignore-file syntax the templates were written in.
+    pub format: Format,
+    /// Whether patterns carry `--annotate` comments.
+    pub annotate: bool,
+}
+
+/// The contents of `.gig.toml`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Lockfile {
+    pub files: Vec<Entry>,
+}
+
+impl Lockfile {
+    /// Load the record at `path`, or None if there is none.
+    pub fn load(path: &Path) -> Result<Option<Lockfile>, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => Lockfile::parse(&content)
+                .map(Some)
+                .map_err(|e| format!("{}: {e}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("failed to read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Lockfile, String> {
+        let doc = toml::parse(content)?;
+        let mut lockfile = Lockfile::default();
+
+        let tables = doc.array_tables.get("file").map_or(&[][..], Vec::as_slice);
+        for (i, table) in tables.iter().enumerate() {
+            let n = i + 1;
+            let path = table
+                .get("path")
+                .and_then(toml::Value::as_str)
+                .ok_or_else(|| format!("file {n}: path: expected a string"))?;
+            let templates = table
+                .get("templates")
+                .and_then(toml::Value::as_string_array)
+                .ok_or_else(|| format!("file {n}: templates: expected an array of strings"))?;
+            let revision = match table.get("revision") {
+                Some(value) => Some(
+                    value
+                        .as_str()
+                        .ok_or_else(|| format!("file {n}: revision: expected a string"))?
+                        .to_string(),
+                ),
+                None => None,
+            };
+            let flag = |key: &str| match table.get(key) {
+                Some(value) => value
+                    .as_bool()
+                    .ok_or_else(|| format!("file {n}: {key}: expected a boolean")),
+                None => Ok(false),
+            };
+            let header = flag("header")?;
+            let dedupe = match table.get("dedupe").map(toml::Value::as_str) {
+                Some(Some("exact")) | None => Dedupe::Exact,
+                Some(Some("semantic")) => Dedupe::Semantic,
+                Some(_) => {
+                    return Err(format!(
+                        "file {n}: dedupe: expected \"exact\" or \"semantic\""
+                    ));
+                }
+            };
+
+            let format = match table.get("format") {
+                Some(value) => value
+                    .as_str()
+                    .ok_or_else(|| "expected a string".to_string())
+                    .and_then(Format::parse)
+                    .map_err(|e| format!("file {n}: format: {e}"))?,
+                None => Format::Gitignore,
+            };
+
+            if lockfile.get(path).is_some() {
+                return Err(format!("file {n}: {path} is listed more than once"));
+            }
+            lockfile.files.push(Entry {
+                path: path.to_string(),
+                templates,
+                revision,
+                header,
+                settings: Settings {
+                    dedupe,
+                    format,
+                    annotate: flag("annotate")?,
+                },
+            });
+        }
+
+        Ok(lockfile)
+    }
+
+    /// The entry for `path`, if recorded.
+    pub fn get(&self, path: &str) -> Option<&Entry> {
+        self.files
+            .iter()
+            .find(|e| normalize(&e.path) == normalize(path))
+    }
+
+    /// Record `entry`, replacing any entry for the same path.
+    pub fn set(&mut self, entry: Entry) {
+        match self
+            .files
+            .iter_mut()
+            .find(|e| normalize(&e.path) == normalize(&entry.path))
+        {
+            Some(existing) => *existing = entry,
+            None => self.files.push(entry),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from(PREAMBLE);
+        for entry in &self.files {
+            let templates: Vec<String> = entry.templates.iter().map(|t| toml::quote(t)).collect();
+            out.push_str("\n[[file]]\n");
+            out.push_str(&format!("path = {}\n", toml::quote(&entry.path)));
+            out.push_str(&format!("templates = [{}]\n", templates.join(", ")));
+            if let Some(revision) = &entry.revision {
+                out.push_str(&format!("revision = {}\n", toml::quote(revision)));
+            }
+            if entry.header {
+                out.push_str("header = true\n");
+            }
+            let settings = &entry.settings;
+            if settings.dedupe == Dedupe::Semantic {
+                out.push_str("dedupe = \"semantic\"\n");
+            }
+            if settings.format != Format::Gitignore {
+                out.push_str(&format!(
+                    "format = {}\n",
+                    toml::quote(settings.format.name())
+                ));
+            }
+            if settings.annotate {
+                out.push_str("annotate = true\n");
+            }
+        }
+        out
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.render())
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+}
+
+/// Where the record for the current directory is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// The record's directory, relative to the current one: empty, `..`, ...
+    pub dir: PathBuf,
+    /// The current directory, relative to the record's.
+    subdir: PathBuf,
+}
+
+impl Location {
+    /// The current directory itself, where a new record goes.
+    pub fn here() -> Location {
+        Location {
+            dir: PathBuf::new(),
+            subdir: PathBuf::new(),
+        }
+    }
+
+    /// Look for the record in `cwd` and its parents, up to the root of the
+    /// git work tree containing it. Outside a work tree only `cwd` is checked.
+    pub fn find(cwd: &Path) -> Option<Location> {
+        let ancestors: Vec<&Path> = cwd.ancestors().collect();
+        let root = ancestors
+            .iter()
+            .position(|dir| dir.join(".git").exists())
+            .unwrap_or(0);
+        let level = ancestors[..=root]
+            .iter()
+            .position(|dir| dir.join(LOCKFILE).is_file())?;
+        Some(Location {
+            dir: std::iter::repeat_n(Component::ParentDir, level).collect(),
+            subdir: cwd.strip_prefix(ancestors[level]).ok()?.to_path_buf(),
+        })
+    }
+
+    /// The record file, relative to the current directory.
+    pub fn file(&self) -> PathBuf {
+        self.dir.join(LOCKFILE)
+    }
+
+    /// A recorded file path, relative to the current directory.
+    pub fn resolve(&self, recorded: &str) -> PathBuf {
+        self.dir.join(recorded)
+    }
+
+    /// How `path`, relative to the current directory, is recorded.
+    pub fn record(&self, path: &Path) -> String {
+        let joined = self.subdir.join(path);
+        let mut parts: Vec<Component> = Vec::new();
+        for component in joined.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir if matches!(parts.last(), Some(Component::Normal(_))) => {
+                    parts.pop();
+                }
+                other => parts.push(other),
+            }
+        }
+        parts
+            .iter()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Compare paths as written, ignoring a leading `./`.
+fn normalize(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gig_lockfile_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_location_find_in_parent() {
+        let root = unique_dir("find");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join(LOCKFILE), "").unwrap();
+
+        let location = Location::find(&root.join("a/b")).unwrap();
+        assert_eq!(location.file(), Path::new("../..").join(LOCKFILE));
+        assert_eq!(
+            location.resolve(".gitignore"),
+            Path::new("../../.gitignore")
+        );
+        assert_eq!(location.record(Path::new(".gitignore")), "a/b/.gitignore");
+        assert_eq!(location.record(Path::new("../.gitignore")), "a/.gitignore");
+
+        let here = Location::find(&root).unwrap();
+        assert_eq!(here.file(), Path::new(LOCKFILE));
+        assert_eq!(here.record(Path::new("./.gitignore")), ".gitignore");
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_location_find_stops_at_git_root() {
+        let outer = unique_dir("stop");
+        fs::write(outer.join(LOCKFILE), "").unwrap();
+        fs::create_dir_all(outer.join("repo/.git")).unwrap();
+        fs::create_dir_all(outer.join("repo/src")).unwrap();
+
+        assert_eq!(Location::find(&outer.join("repo/src")), None);
+        // Outside a work tree only the directory itself counts
+        fs::remove_dir_all(outer.join("repo/.git")).unwrap();
+        assert_eq!(Location::find(&outer.join("repo/src")), None);
+        assert!(Location::find(&outer).is_some());
+
+        fs::remove_dir_all(&outer).ok();
+    }
+
+    fn entry(path: &str, templates: &[&str]) -> Entry {
+        Entry {
+            path: path.to_string(),
+            templates: templates.iter().map(|t| t.to_string()).collect(),
+            revision: None,
+            header: false,
+            settings: Settings::default(),
+        }
+    }
+
+    #[test]
+    fn test_render_parse_round_trip() {
+        let mut lockfile = Lockfile::default();
+        lockfile.set(entry(".gitignore", &["python", "global.macos"]));
+        lockfile.set(Entry {
+            settings: Settings {
+                format: Format::Dockerignore,
+                annotate: true,
+                ..Settings::default()
+            },
+            ..entry(".dockerignore", &["python"])
+        });
+        lockfile.set(Entry {
+            revision: Some("github/gitignore at 0123abcd4567 (2024-05-01)".to_string()),
+            header: true,
+            settings: Settings {
+                dedupe: Dedupe::Semantic,
+                ..Settings::default()
+            },
+            ..entry("docs/.gitignore", &["node"])
+        });
+
+        let parsed = Lockfile::parse(&lockfile.render()).unwrap();
+        assert_eq!(parsed, lockfile);
+    }
+
+    #[test]
+    fn test_set_replaces_same_path() {
+        let mut lockfile = Lockfile::default();
+        lockfile.set(entry(".gitignore", &["python"]));
+        lockfile.set(entry("./.gitignore", &["go"]));
+        assert_eq!(lockfile.files.len(), 1);
+        assert_eq!(lockfile.get(".gitignore").unwrap().templates, vec!["go"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = Lockfile::parse("[[file]]\ntemplates = []\n").unwrap_err();
+        assert!(err.contains("file 1: path"), "got: {err}");
+
+        let err = Lockfile::parse("[[file]]\npath = \"a\"\ntemplates = \"go\"\n").unwrap_err();
+        assert!(err.contains("file 1: templates"), "got: {err}");
+
+        let twice =
+            "[[file]]\npath = \"a\"\ntemplates = []\n[[file]]\npath = \"./a\"\ntemplates = []\n";
+        let err = Lockfile::parse(twice).unwrap_err();
+        assert!(err.contains("more than once"), "got: {err}");
+
+        let err = Lockfile::parse("[[file]]\npath = \"a\"\ntemplates = []\ndedupe = \"fuzzy\"\n")
+            .unwrap_err();
+        assert!(err.contains("file 1: dedupe"), "got: {err}");
+
+        let err =
+            Lockfile::parse("[[file]]\npath = \"a\"\ntemplates = []\nformat = \"svnignore\"\n")
+                .unwrap_err();
+        assert!(err.contains("file 1: format: unknown format"), "got: {err}");
+    }
+
+    #[test]
+    fn test_load_missing() {
+        assert_eq!(
+            Lockfile::load(Path::new("/nonexistent/.gig.toml")).unwrap(),
+            None
+        );
+    }
+}
@@ -1,29 +1,126 @@
+use config::Config;
+use convert::Format;
 use include_dir::{Dir, include_dir};
+use json::{ErrorCode, WithCode};
+use layout::GITIGNORE_SUFFIX;
+use lockfile::{LOCKFILE, Location, Lockfile, Settings};
+use merge::{Dedupe, LineKind};
+use search::{scope_label, template_scope};
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs::OpenOptions;
-use std::io::{ErrorKind, Write};
+use std::io::{ErrorKind, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process;
 use std::sync::LazyLock;
 
+mod blocks;
+mod config;
+mod convert;
+mod detect;
+mod diff;
+mod format;
+mod git;
+mod json;
+mod layout;
+mod lint;
+mod lockfile;
+mod matcher;
+mod merge;
+mod normalize;
+mod preview;
+mod search;
+mod suggest;
+mod toml;
+mod update;
+
 const DEFAULT_OUTPUT: &str = ".gitignore";
-const GITIGNORE_SUFFIX: &str = ".gitignore";
+const STDOUT_PATH: &str = "-";
+const TEMPLATE_PATH_ENV: &str = "GIG_TEMPLATE_PATH";
+const MAX_SUGGESTIONS: usize = 5;
+/// Upstream revision of the embedded templates, recorded by build.rs when known.
+const TEMPLATES_COMMIT: Option<&str> = option_env!("GIG_TEMPLATES_COMMIT");
+const TEMPLATES_DATE: Option<&str> = option_env!("GIG_TEMPLATES_DATE");
 const LANG_REQUIRED_ERR: &str = "languages required (e.g., gig python or gig go,godot,node)";
 
 const HELP_MSG: &str = r#"gig - generate .gitignore files from GitHub's template collection
 
 Usage:
   gig <languages> [output]
+  gig remove <languages> [output]
+  gig search <term>
+  gig show <template> [--no-pager]
+  gig auto [output]
+  gig update [source]
+  gig sync | gig check
+  gig check-ignore [--templates <languages> | --file <path>] <paths>...
+  gig preview <languages> [dir] [--tracked]
+  gig lint [--fix] [file]
+  gig fmt [--sort] [--check] [file]
+  gig explain <file> <pattern>
 
 Arguments:
-  languages  Comma-separated list of language/tool templates (e.g., python or go,godot,node)
-  output     Path to write the .gitignore file (default: .gitignore)
+  languages  Comma-separated list of language/tool templates (e.g., python or go,godot,node),
+             presets (@name) and aliases from ~/.config/gig/config.toml
+  output     Path to write the .gitignore file (default: .gitignore), or - for stdout
+
+Commands:
+  remove     Remove templates' blocks (or their lines) from an existing file
+  search     Find templates by name, scope or the patterns they contain
+  show       Print a template with its scope, upstream path, size and related
+             templates, paged through $PAGER on a terminal
+  auto       Same as --detect
+  sync       Regenerate the files recorded in .gig.toml
+  check      Exit non-zero if recorded files differ from what sync would write
+             (--diff shows how)
+  check-ignore
+             Show whether paths are ignored by .gitignore (or --file, or a
+             --templates set) and which line decides it
+  preview    List files in a directory (default .) that adding templates to
+             its .gitignore would newly ignore; --tracked also lists tracked
+             files the new patterns match
+  lint       Report problems in an existing .gitignore (or a given file):
+             shadowed and duplicate lines, negations that can't apply,
+             stray whitespace, regex syntax, CRLF endings; --fix removes the
+             ones that can go without changing what is ignored
+  fmt        Rewrite an existing .gitignore (or a given file) in canonical form:
+             blank runs collapsed, trailing spaces trimmed, repeats dropped;
+             --sort sorts patterns within sections, --check only verifies
+  explain    Show where a pattern in a generated file came from:
+             gig explain <file> <pattern>
+  update     Fetch newer templates (git URL, tarball or directory) into the cache
 
 Flags:
-  --list         List all available language templates
-  --append       Append to existing file, deduplicating patterns
+  --list         List all available language templates, presets and aliases
+  --detect       Pick templates from marker files (Cargo.toml, package.json, ...)
+                 in the output directory instead of taking a language list
+  --append       Merge into existing file, regenerating gig blocks in place
+  --stdout       Write to stdout (same as an output path of -)
+  --dry-run      Print the result to stdout instead of writing the file
+  --strict       Fail instead of warning when new patterns match files git
+                 already tracks
+  --format=<name>
+                 Write another ignore syntax: dockerignore, npmignore,
+                 prettierignore, eslintignore, gcloudignore, helmignore or
+                 hgignore (default output: .<name>)
+  --target=exclude|global
+                 Merge into this repository's .git/info/exclude, or into your
+                 global excludes file (core.excludesFile, else
+                 ~/.config/git/ignore) instead of a .gitignore
+  --annotate     Add a comment above each pattern that later templates repeated,
+                 naming them
+  --dedupe=semantic
+                 Also drop patterns an earlier line already covers (build/**
+                 after build/), and report each one dropped
+  --lock         Record the templates used in .gig.toml (updated automatically
+                 once it exists)
+  --header       Start the file with a comment naming the gig version and
+                 template revision (kept up to date once present)
+  --diff         Print a unified diff against the file on disk instead of writing
+  --json         Print --list, show and generation results as JSON, and errors
+                 as {"error":{"code":...,"message":...}} on stderr
   -h, --help     Show this help message
-  -V, --version  Show version information
+  -V, --version  Show version information (with --verbose, template revisions)
 
 Examples:
   gig python                          Create .gitignore for Python
@@ -32,11 +129,49 @@ Examples:
   gig python,global.macos             Python + macOS global ignores
   gig rust,community.golang.hugo      Rust + Hugo community template
   gig --append node                   Add Node patterns to existing .gitignore
-
-Templates are sourced from https://github.com/github/gitignore"#;
+  gig remove node                     Remove Node patterns from .gitignore
+  gig --append --diff rust            Preview adding Rust as a diff
+  gig search .terraform               Which templates ignore .terraform?
+  gig show hugo                       Read the Hugo template and its metadata
+  gig check-ignore --templates python dist/ app.py
+                                      Would the Python template ignore these?
+  gig preview python,node             What would these templates hide here?
+  gig lint --fix                      Clean up the .gitignore in this directory
+  gig fmt --sort --check              Fail if .gitignore isn't formatted (CI)
+  gig explain .gitignore '*.log'      Which templates put *.log there?
+  gig update                          Refresh templates from GitHub
+  gig update file:///srv/gitignore    Refresh from a local mirror
+  gig --detect                        Detect the project's languages and generate
+  gig @team-rust,python               Expand a preset from the config file
+  gig --lock python,global.macos      Generate and record in .gig.toml
+  gig --format=dockerignore node      Create .dockerignore for Node
+  gig --target=global global.macos    Keep macOS files out of every repository
+  gig --dedupe=semantic rust,jetbrains
+                                      Drop equivalent patterns across templates
+  gig check                           Verify ignore files match .gig.toml (CI)
+  gig --json python,node              Generate and print a JSON summary
+  gig python - | tee a/.gitignore     Write to stdout
+  gig --append node - < .gitignore    Filter: read existing content from stdin
+
+Each template is written between '# >>> gig: <name>' and '# <<< gig: <name>'
+markers. With --append, blocks already in the file are regenerated in place and
+lines outside them are left untouched. When writing to stdout, --append and
+remove read the existing content from stdin.
+
+Templates are sourced from https://github.com/github/gitignore. After
+'gig update', the cached set in ~/.cache/gig replaces the embedded one.
+Directories in GIG_TEMPLATE_PATH and the config file's template_path add local
+templates, which shadow embedded or cached ones with the same name."#;
+
+/// Entry point of a subcommand, given the arguments after its name and
+/// whether `--json` output was asked for.
+type Subcommand = fn(&mut pico_args::Arguments, bool) -> Result<(), json::Error>;
 
 static TEMPLATES: Dir<'_> = include_dir!("$OUT_DIR/templates");
-static INDEX: LazyLock<HashMap<String, &'static str>> = LazyLock::new(build_index);
+/// `<file name>\t<upstream path>` for each embedded template, from `build.rs`.
+static UPSTREAM: &str = include_str!(concat!(env!("OUT_DIR"), "/upstream.tsv"));
+static INDEX: LazyLock<HashMap<String, Template>> = LazyLock::new(build_index);
+static CONFIG: LazyLock<Result<Config, String>> = LazyLock::new(Config::load);
 
 fn main() {
     let mut args = pico_args::Arguments::from_env();
@@ -50,45 +185,185 @@ fn main() {
     // Handle --version / -V
     if args.contains(["-V", "--version"]) {
         println!("gig {}", env!("CARGO_PKG_VERSION"));
+        if args.contains("--verbose") {
+            print_template_versions();
+        }
         process::exit(0);
     }
 
+    // Handle --json
+    let json = args.contains("--json");
+
     // Handle --list
     if args.contains("--list") {
-        list_languages();
+        let listed = if json {
+            list_languages_json()
+        } else {
+            list_languages()
+        };
+        if let Err(e) = listed {
+            fail(json, ErrorCode::Config, &e);
+        }
         process::exit(0);
     }
 
+    // Dispatch subcommands; anything else is a language list
+    let mut rest = args.finish();
+    let command: Option<Subcommand> = match rest.first().and_then(|a| a.to_str()) {
+        Some("remove") => Some(run_remove),
+        Some("show") => Some(run_show),
+        Some("search") => Some(run_search),
+        Some("update") => Some(run_update),
+        Some("sync") => Some(run_sync),
+        Some("check") => Some(run_check),
+        Some("check-ignore") => Some(run_check_ignore),
+        Some("preview") => Some(run_preview),
+        Some("lint") => Some(run_lint),
+        Some("fmt") => Some(run_fmt),
+        Some("explain") => Some(run_explain),
+        _ => None,
+    };
+    if let Some(run) = command {
+        rest.remove(0);
+        let mut args = pico_args::Arguments::from_vec(rest);
+        if let Err(e) = run(&mut args, json) {
+            fail(json, e.code, &e.message);
+        }
+        process::exit(0);
+    }
+    let auto_command = rest.first().and_then(|a| a.to_str()) == Some("auto");
+    if auto_command {
+        rest.remove(0);
+    }
+    let mut args = pico_args::Arguments::from_vec(rest);
+
+    // Handle --detect / auto
+    let detect_mode = auto_command || args.contains("--detect");
+
     // Handle --append
     let append_mode = args.contains("--append");
 
+    // Handle --header
+    let header_mode = args.contains("--header");
+
+    // Handle --lock
+    let lock_mode = args.contains("--lock");
+
+    // Handle --strict
+    let strict_mode = args.contains("--strict");
+
+    // Handle --annotate
+    let annotate = args.contains("--annotate");
+
+    // Handle --dedupe
+    let dedupe = match parse_dedupe(&mut args) {
+        Ok(d) => d,
+        Err(e) => fail(json, ErrorCode::Usage, &e),
+    };
+
+    // Handle --format
+    let format = match parse_format(&mut args) {
+        Ok(f) => f,
+        Err(e) => fail(json, ErrorCode::Usage, &e),
+    };
+
+    // Handle --dry-run / --diff
+    let mode = match parse_output_mode(&mut args) {
+        Ok(m) => m,
+        Err(e) => fail(json, ErrorCode::Usage, &e),
+    };
+
+    // Handle --target
+    let target = match parse_target(&mut args) {
+        Ok(t) => t,
+        Err(e) => fail(json, ErrorCode::Usage, &e),
+    };
+    if target.is_some() {
+        let conflict = if detect_mode {
+            Some("--detect")
+        } else if lock_mode {
+            Some("--lock")
+        } else if format != Format::Gitignore {
+            Some("--format")
+        } else {
+            None
+        };
+        if let Some(flag) = conflict {
+            fail(
+                json,
+                ErrorCode::Usage,
+                &format!("--target cannot be combined with {flag}"),
+            );
+        }
+    }
+    // Excludes files are shared with other tools, so always merge into them
+    let append_mode = append_mode || target.is_some();
+
     // Parse languages and output path
-    let (languages, output) = match parse_args(&mut args) {
+    let default_output = target
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format.file_name()));
+    let parsed = if detect_mode {
+        parse_detect_args(&mut args, &default_output)
+    } else {
+        parse_args(&mut args, &default_output)
+    };
+    let (languages, output) = match parsed {
         Ok((l, o)) => (l, o),
-        Err(e) => {
-            eprintln!("error: {e}");
-            process::exit(1);
-        }
+        Err(e) => fail(json, ErrorCode::Usage, &e),
+    };
+
+    if lock_mode && is_stdout(&output) {
+        fail(
+            json,
+            ErrorCode::Usage,
+            &format!("--lock needs an output file to record in {LOCKFILE}"),
+        );
+    }
+    if target.is_some() && output != default_output {
+        fail(
+            json,
+            ErrorCode::Usage,
+            "--target chooses the output file; drop the output path",
+        );
+    }
+    // The summary goes to stdout, so the result can't
+    if json && (is_stdout(&output) || mode != OutputMode::Write) {
+        fail(
+            json,
+            ErrorCode::Usage,
+            "--json needs an output file, and can't be combined with --dry-run or --diff",
+        );
+    }
+
+    // Expand presets and aliases from the user config
+    let languages = match config().and_then(|c| c.expand(&languages)) {
+        Ok(l) => l,
+        Err(e) => fail(json, ErrorCode::Config, &e),
     };
 
     // If appending, read the existing file content
     let existing_content = if append_mode {
         match read_existing_file(&output) {
             Ok(content) => content,
-            Err(e) => {
-                eprintln!("error: {e}");
-                process::exit(1);
-            }
+            Err(e) => fail(json, ErrorCode::Io, &e),
         }
     } else {
         None
     };
 
-    // Get template content for each language
-    let mut templates: Vec<&str> = Vec::new();
+    // Get template content for each language, keyed by its block name and
+    // translated for the output format
+    let mut templates: Vec<(String, String)> = Vec::new();
+    let mut notes = 0;
     for lang in &languages {
-        match get_template(lang) {
-            Ok(c) => templates.push(c),
+        match resolve_template(lang) {
+            Ok((key, c)) => {
+                let translated = convert::translate(c, format);
+                notes += translated.notes;
+                templates.push((key.to_string(), translated.content));
+            }
+            Err(e) if json => fail(json, ErrorCode::UnknownTemplate, &e),
             Err(e) => {
                 eprintln!("error: {e}");
                 eprintln!("\nRun 'gig --list' to see available languages.");
@@ -96,21 +371,1113 @@ fn main() {
             }
         }
     }
+    if notes > 0 {
+        eprintln!(
+            "warning: {notes} patterns can't be written exactly as {}; see the '{}' comments",
+            format.file_name(),
+            convert::NOTE_PREFIX.trim_end()
+        );
+    }
+
+    // Merge templates into managed blocks (existing content wins dedup)
+    let named: Vec<(&str, &str)> = templates
+        .iter()
+        .map(|(n, c)| (n.as_str(), c.as_str()))
+        .collect();
+    let (content, dropped) =
+        match blocks::merge_blocks_with(existing_content.as_deref(), &named, dedupe, annotate) {
+            Ok(merged) => merged,
+            Err(e) => fail(
+                json,
+                ErrorCode::InvalidFile,
+                &format!("{}: {e}", output.display()),
+            ),
+        };
+    for d in &dropped {
+        eprintln!(
+            "dropped \"{}\" (covered by \"{}\")",
+            d.pattern, d.covered_by
+        );
+    }
+
+    // Point out templates that undo each other's patterns
+    for conflict in blocks::conflicts(&content) {
+        let (earlier, later) = (&conflict.earlier, &conflict.later);
+        eprintln!(
+            "warning: {} \"{}\" (line {}) overrides {} \"{}\" (line {})",
+            later.block, later.pattern, later.line, earlier.block, earlier.pattern, earlier.line
+        );
+    }
+
+    // Record the template revision, refreshing a header from an earlier run
+    let content = blocks::set_header(&content, &header_line(), header_mode);
+    let content = convert::add_preamble(&content, format);
+
+    // Flag tracked files that the new patterns can't affect
+    if format == Format::Gitignore
+        && let Err(e) = check_tracked(&output, existing_content.as_deref(), &content, strict_mode)
+    {
+        fail(json, ErrorCode::TrackedFiles, &e);
+    }
+
+    // info/ or ~/.config/git may not exist yet
+    if target.is_some()
+        && mode == OutputMode::Write
+        && let Some(parent) = output.parent()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        fail(
+            json,
+            ErrorCode::Io,
+            &format!("failed to create {}: {e}", parent.display()),
+        );
+    }
+
+    if let Err(e) = emit_output(&output, &content, append_mode, mode) {
+        let code = if !append_mode && output.exists() {
+            ErrorCode::FileExists
+        } else {
+            ErrorCode::Io
+        };
+        fail(json, code, &e);
+    }
+    if target.is_some() && mode == OutputMode::Write {
+        eprintln!("wrote {}", output.display());
+    }
+
+    // Keep .gig.toml up to date, creating it with --lock; excludes files
+    // aren't recorded
+    if target.is_none()
+        && let Err(e) = record_output(
+            &output,
+            &content,
+            Some(Settings {
+                dedupe,
+                format,
+                annotate,
+            }),
+            lock_mode,
+            mode,
+        )
+    {
+        fail(json, ErrorCode::Io, &e);
+    }
+
+    if json {
+        println!(
+            "{}",
+            generate_summary(
+                &output,
+                format,
+                &templates,
+                existing_content.as_deref(),
+                &content
+            )
+        );
+    }
+}
+
+/// Report an error, as a `--json` error object if `json` is set, and exit.
+fn fail(json: bool, code: ErrorCode, message: &str) -> ! {
+    if json {
+        eprintln!("{}", json::error(code, message));
+    } else {
+        eprintln!("error: {message}");
+    }
+    process::exit(1);
+}
+
+/// The `--json` summary of a run: the output file, the templates used, how
+/// many of their patterns are new to the file (not in `existing`) and how
+/// many were deduplicated away.
+fn generate_summary(
+    output: &Path,
+    format: Format,
+    templates: &[(String, String)],
+    existing: Option<&str>,
+    content: &str,
+) -> json::Value {
+    let offered: usize = templates.iter().map(|(_, t)| count_patterns(t)).sum();
+    let before: HashSet<&str> = existing
+        .unwrap_or_default()
+        .lines()
+        .filter_map(blocks::pattern_of)
+        .collect();
+    let line_blocks = blocks::line_blocks(content);
+    let written: Vec<&str> = content
+        .lines()
+        .zip(&line_blocks)
+        .filter(|(_, block)| block.is_some_and(|b| templates.iter().any(|(name, _)| name == b)))
+        .filter_map(|(line, _)| blocks::pattern_of(line))
+        .collect();
+    let added = written.iter().filter(|p| !before.contains(*p)).count();
+    let names: Vec<&str> = templates.iter().map(|(name, _)| name.as_str()).collect();
+
+    json::Value::Object(vec![
+        ("output", output.display().to_string().into()),
+        ("format", format.name().into()),
+        ("templates", names.into()),
+        ("patterns_added", added.into()),
+        (
+            "patterns_deduplicated",
+            offered.saturating_sub(written.len()).into(),
+        ),
+    ])
+}
+
+/// The number of pattern lines in `content`.
+fn count_patterns(content: &str) -> usize {
+    content
+        .lines()
+        .filter(|l| matches!(LineKind::of(l), LineKind::Pattern(_)))
+        .count()
+}
+
+/// Warn about files git tracks that the patterns added by this run would
+/// ignore, since ignoring doesn't apply to tracked files. With `strict`, fail
+/// instead. Only applies to output files inside a git work tree.
+fn check_tracked(
+    output: &Path,
+    existing: Option<&str>,
+    content: &str,
+    strict: bool,
+) -> Result<(), String> {
+    if is_stdout(output) {
+        return Ok(());
+    }
+    let dir = match output.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !git::is_work_tree(dir) {
+        return Ok(());
+    }
+
+    let tracked = git::tracked_files(dir)?;
+    let hits = preview::tracked_newly_ignored(existing.unwrap_or_default(), content, &tracked);
+    if hits.is_empty() {
+        return Ok(());
+    }
+
+    let line_blocks = blocks::line_blocks(content);
+    let level = if strict { "error" } else { "warning" };
+    eprintln!(
+        "{level}: {} tracked files match new patterns in {}; git keeps tracking them:",
+        hits.len(),
+        output.display()
+    );
+    for hit in &hits {
+        match line_blocks.get(hit.line - 1).copied().flatten() {
+            Some(block) => eprintln!("  {} ({block}: {})", hit.path, hit.pattern),
+            None => eprintln!("  {} ({})", hit.path, hit.pattern),
+        }
+    }
+    if strict {
+        return Err(
+            "not writing because of --strict; untrack them with 'git rm --cached' first"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// How a run delivers its result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputMode {
+    /// Write the output file.
+    Write,
+    /// Print the full result to stdout.
+    DryRun,
+    /// Print a unified diff between the file on disk and the result.
+    Diff,
+}
+
+/// Parse the --dry-run and --diff flags.
+fn parse_output_mode(args: &mut pico_args::Arguments) -> Result<OutputMode, String> {
+    match (args.contains("--dry-run"), args.contains("--diff")) {
+        (true, true) => Err("--dry-run and --diff cannot be used together".to_string()),
+        (true, false) => Ok(OutputMode::DryRun),
+        (false, true) => Ok(OutputMode::Diff),
+        (false, false) => Ok(OutputMode::Write),
+    }
+}
+
+/// Deliver content according to `mode`. Preview modes never touch the file.
+fn emit_output(
+    path: &Path,
+    content: &str,
+    allow_overwrite: bool,
+    mode: OutputMode,
+) -> Result<(), String> {
+    if mode == OutputMode::Write || (mode == OutputMode::DryRun && is_stdout(path)) {
+        return write_output(path, content, allow_overwrite);
+    }
+    if is_stdout(path) {
+        return Err("--diff needs an output file to compare against".to_string());
+    }
+
+    // Fail the same way a real write would
+    let current = read_existing_file(path)?;
+    if current.is_some() && !allow_overwrite {
+        return Err(already_exists_error(path));
+    }
+
+    if mode == OutputMode::DryRun {
+        print!("{content}");
+    } else {
+        let new_label = path.display().to_string();
+        let old_label = match current {
+            Some(_) => new_label.as_str(),
+            None => "/dev/null",
+        };
+        let old = current.as_deref().unwrap_or("");
+        print!(
+            "{}",
+            diff::unified_diff(old, content, old_label, &new_label)
+        );
+    }
+    Ok(())
+}
+
+/// Remove templates from an existing file and report what was removed.
+fn run_remove(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let after = split_at_dashes(args);
+    let mode = parse_output_mode(args).code(ErrorCode::Usage)?;
+    let to_stdout = args.contains("--stdout");
+    let mut free = positionals(args, after, 2)?.into_iter();
+    let languages = free
+        .next()
+        .ok_or(LANG_REQUIRED_ERR)
+        .map_err(String::from)
+        .and_then(|l| parse_languages(&l))
+        .code(ErrorCode::Usage)?;
+    let output = output_path(free.next().map(PathBuf::from), to_stdout, DEFAULT_OUTPUT)
+        .code(ErrorCode::Usage)?;
+    let languages = config()
+        .and_then(|c| c.expand(&languages))
+        .code(ErrorCode::Config)?;
+    let existing = read_existing(&output)?;
+
+    // Resolve names where possible; blocks for unknown templates match as written
+    let names: Vec<String> = languages
+        .iter()
+        .map(|l| match resolve_template(l) {
+            Ok((key, _)) => key.to_string(),
+            Err(_) => l.to_lowercase(),
+        })
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let settings = written_settings(&output, &existing).code(ErrorCode::Config)?;
+    // Only lines outside blocks need protecting, so skip the scan when every
+    // template has a block
+    let in_blocks = blocks::block_names(&existing).unwrap_or_default();
+    let kept = if names.iter().all(|n| in_blocks.iter().any(|b| b == n)) {
+        Vec::new()
+    } else {
+        templates_present(&existing, &names, settings.format, &INDEX)
+    };
+    let (content, report) = blocks::remove_templates(
+        &existing,
+        &names,
+        &kept,
+        &|name| translated_template(name, settings.format),
+        settings.dedupe,
+        settings.annotate,
+    )
+    .code(ErrorCode::InvalidFile)
+    .map_err(|e| e.context(output.display()))?;
+    let content = blocks::set_header(&content, &header_line(), false);
+    let content = convert::add_preamble(&content, settings.format);
+
+    emit_output(&output, &content, true, mode).code(ErrorCode::Io)?;
+    record_output(&output, &content, None, false, mode).code(ErrorCode::Io)?;
+
+    // Report on stderr so it doesn't mix with --dry-run / --diff output
+    let verb = if mode == OutputMode::Write {
+        "removed"
+    } else {
+        "would remove"
+    };
+    for (name, removed) in report {
+        match removed {
+            blocks::Removed::Block(n) => {
+                eprintln!("{verb} {name} block ({n} lines) from {}", output.display())
+            }
+            blocks::Removed::Lines(0) => {
+                eprintln!("{name}: nothing to remove from {}", output.display())
+            }
+            blocks::Removed::Lines(n) => {
+                eprintln!("{verb} {n} {name} lines from {}", output.display())
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Regenerate every file recorded in `.gig.toml`.
+fn run_sync(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let mode = parse_output_mode(args).code(ErrorCode::Usage)?;
+    let (location, mut lockfile) = load_lockfile()?;
+
+    for entry in &mut lockfile.files {
+        let path = location.resolve(&entry.path);
+        let existing = read_existing_file(&path).code(ErrorCode::Io)?;
+        let content =
+            regenerate(entry, existing.as_deref()).map_err(|e| e.context(path.display()))?;
+        emit_output(&path, &content, true, mode).code(ErrorCode::Io)?;
+
+        if mode == OutputMode::Write {
+            if existing.as_deref() == Some(content.as_str()) {
+                eprintln!("{} is up to date", path.display());
+            } else {
+                eprintln!("updated {}", path.display());
+            }
+            entry.revision = Some(template_revision());
+        }
+    }
+
+    if mode == OutputMode::Write {
+        lockfile.save(&location.file()).code(ErrorCode::Io)?;
+    }
+    Ok(())
+}
+
+/// Fail if any file recorded in `.gig.toml` differs from what `gig sync`
+/// would write, or was recorded from other templates than the current ones.
+fn run_check(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let show_diff = args.contains("--diff");
+    let (location, lockfile) = load_lockfile()?;
+    let current = template_revision();
+
+    let mut stale = 0;
+    for entry in &lockfile.files {
+        let path = location.resolve(&entry.path);
+        let name = path.to_string_lossy();
+        let existing = read_existing_file(&path).code(ErrorCode::Io)?;
+        let expected = regenerate(entry, existing.as_deref()).map_err(|e| e.context(&name))?;
+        let drifted = entry.revision.as_ref().filter(|r| **r != current);
+
+        match existing.as_deref() {
+            Some(content) if content == expected && drifted.is_none() => {
+                println!("{name}: up to date")
+            }
+            Some(content) if content == expected => {}
+            Some(content) => {
+                println!("{name}: out of date");
+                if show_diff {
+                    print!("{}", diff::unified_diff(content, &expected, &name, &name));
+                }
+            }
+            None => println!("{name}: missing"),
+        }
+        if let Some(revision) = drifted {
+            println!("{name}: recorded from {revision}, templates are now {current}");
+        }
+        if existing.as_deref() != Some(expected.as_str()) || drifted.is_some() {
+            stale += 1;
+        }
+    }
+
+    if stale > 0 {
+        return Err(json::Error::new(
+            ErrorCode::Failed,
+            format!(
+                "{stale} of {} files out of sync with {LOCKFILE}; run 'gig sync'",
+                lockfile.files.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// The project's `.gig.toml`, searched for from the current directory up to
+/// the root of its git work tree.
+fn find_lockfile() -> Option<Location> {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| Location::find(&cwd))
+}
+
+fn load_lockfile() -> Result<(Location, Lockfile), json::Error> {
+    let missing = || format!("no {LOCKFILE} found; create one by generating with --lock");
+    let location = find_lockfile()
+        .ok_or_else(missing)
+        .code(ErrorCode::Config)?;
+    let lockfile = Lockfile::load(&location.file())
+        .and_then(|l| l.ok_or_else(missing))
+        .code(ErrorCode::Config)?;
+    Ok((location, lockfile))
+}
+
+/// The content `gig sync` writes for a recorded file: its blocks regenerated
+/// (and blocks no longer recorded dropped), hand-written lines kept.
+fn regenerate(entry: &lockfile::Entry, existing: Option<&str>) -> Result<String, json::Error> {
+    let mut content = existing.unwrap_or_default().to_string();
+    let stale: Vec<String> = blocks::block_names(&content)
+        .code(ErrorCode::InvalidFile)?
+        .into_iter()
+        .filter(|name| !entry.templates.contains(name))
+        .collect();
+    let settings = entry.settings;
+    if !stale.is_empty() {
+        let names: Vec<&str> = stale.iter().map(String::as_str).collect();
+        let kept: Vec<&str> = entry.templates.iter().map(String::as_str).collect();
+        content = blocks::remove_templates(
+            &content,
+            &names,
+            &kept,
+            &|name| translated_template(name, settings.format),
+            settings.dedupe,
+            settings.annotate,
+        )
+        .code(ErrorCode::InvalidFile)?
+        .0;
+    }
+
+    let mut translated: Vec<(&str, String)> = Vec::new();
+    for key in &entry.templates {
+        translated.push((
+            key,
+            translated_template(key, settings.format).code(ErrorCode::UnknownTemplate)?,
+        ));
+    }
+    let templates: Vec<(&str, &str)> = translated.iter().map(|(k, c)| (*k, c.as_str())).collect();
+    let (content, _) = blocks::merge_blocks_with(
+        Some(&content),
+        &templates,
+        settings.dedupe,
+        settings.annotate,
+    )
+    .code(ErrorCode::InvalidFile)?;
+    let content = blocks::set_header(&content, &header_line(), entry.header);
+    Ok(convert::add_preamble(&content, settings.format))
+}
+
+/// A template as a file in `format` has it.
+fn translated_template(name: &str, format: Format) -> Result<String, String> {
+    get_template(name).map(|t| convert::translate(t, format).content)
+}
+
+/// Templates in `index` other than `removed` that `content` appears to have
+/// outside managed blocks: all of their patterns are there, including one
+/// that the removed templates lack.
+fn templates_present<'a>(
+    content: &str,
+    removed: &[&str],
+    format: Format,
+    index: &'a HashMap<String, Template>,
+) -> Vec<&'a str> {
+    let in_file: HashSet<&str> = content.lines().filter_map(blocks::pattern_of).collect();
+    let removed_templates: Vec<String> = removed
+        .iter()
+        .filter_map(|name| index.get(*name))
+        .map(|t| convert::translate(t.content, format).content)
+        .collect();
+    let removed_patterns: HashSet<&str> = removed_templates
+        .iter()
+        .flat_map(|t| t.lines().filter_map(blocks::pattern_of))
+        .collect();
+
+    index
+        .iter()
+        .filter(|(key, _)| !removed.contains(&key.as_str()))
+        .filter(|(_, template)| {
+            let translated = convert::translate(template.content, format).content;
+            let patterns: Vec<&str> = translated.lines().filter_map(blocks::pattern_of).collect();
+            patterns.iter().all(|p| in_file.contains(p))
+                && patterns.iter().any(|p| !removed_patterns.contains(p))
+        })
+        .map(|(key, _)| key.as_str())
+        .collect()
+}
+
+/// The settings the file at `path` was written with: as recorded in
+/// `.gig.toml`, or else the format its name suggests and whether it has
+/// `--annotate` comments.
+fn written_settings(path: &Path, content: &str) -> Result<Settings, String> {
+    if let Some(location) = find_lockfile()
+        && let Some(lockfile) = Lockfile::load(&location.file())?
+        && let Some(entry) = lockfile.get(&location.record(path))
+    {
+        return Ok(entry.settings);
+    }
+    Ok(Settings {
+        format: Format::for_path(path),
+        annotate: content
+            .lines()
+            .any(|l| l.starts_with(blocks::ALSO_IN_PREFIX)),
+        ..Settings::default()
+    })
+}
+
+/// Record the templates in a written file in `.gig.toml`, if the project has
+/// one or `create` is set. Without `settings`, the recorded ones are kept.
+fn record_output(
+    path: &Path,
+    content: &str,
+    settings: Option<Settings>,
+    create: bool,
+    mode: OutputMode,
+) -> Result<(), String> {
+    if is_stdout(path) {
+        return match create {
+            true => Err(format!(
+                "--lock needs an output file to record in {LOCKFILE}"
+            )),
+            false => Ok(()),
+        };
+    }
+    if mode != OutputMode::Write {
+        return Ok(());
+    }
+
+    let (location, lockfile) = match find_lockfile() {
+        Some(location) => {
+            let lockfile = Lockfile::load(&location.file())?;
+            (location, lockfile)
+        }
+        None => (Location::here(), None),
+    };
+    let mut lockfile = match lockfile {
+        Some(lockfile) => lockfile,
+        None if create => Lockfile::default(),
+        None => return Ok(()),
+    };
+    let path = location.record(path);
+    let settings = settings
+        .or_else(|| lockfile.get(&path).map(|e| e.settings))
+        .unwrap_or_default();
+    lockfile.set(lockfile::Entry {
+        path,
+        templates: blocks::block_names(content)?,
+        revision: Some(template_revision()),
+        header: content.starts_with(blocks::HEADER_PREFIX),
+        settings,
+    });
+    lockfile.save(&location.file())
+}
+
+/// Report whether paths would be ignored by a .gitignore file or a candidate
+/// template set, and which line decides it.
+fn run_check_ignore(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let after = split_at_dashes(args);
+    let languages: Option<String> = args
+        .opt_value_from_str("--templates")
+        .map_err(|e| e.to_string())
+        .code(ErrorCode::Usage)?;
+    let file: Option<PathBuf> = args
+        .opt_value_from_str("--file")
+        .map_err(|e| e.to_string())
+        .code(ErrorCode::Usage)?;
+    let paths = positionals(args, after, usize::MAX)?;
+    if paths.is_empty() {
+        return Err(json::Error::new(
+            ErrorCode::Usage,
+            "paths required (e.g., gig check-ignore build/ src/main.rs)",
+        ));
+    }
+
+    let (label, content, base) = match (languages, file) {
+        (Some(_), Some(_)) => {
+            return Err(json::Error::new(
+                ErrorCode::Usage,
+                "--templates and --file conflict",
+            ));
+        }
+        (Some(languages), None) => {
+            let languages = parse_languages(&languages).code(ErrorCode::Usage)?;
+            (
+                "templates".to_string(),
+                generate(&languages, None)?,
+                PathBuf::from("."),
+            )
+        }
+        (None, file) => {
+            let file = file.unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT));
+            let content = read_existing(&file)?;
+            let base = match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            (file.display().to_string(), content, base)
+        }
+    };
+
+    let matcher = matcher::Matcher::parse(&content);
+    let line_blocks = blocks::line_blocks(&content);
+    for path in &paths {
+        let is_dir = path.ends_with('/') || base.join(path).is_dir();
+        let Some(pattern) = matcher.decide(path, is_dir) else {
+            println!("{path}: not ignored");
+            continue;
+        };
+
+        let verdict = if pattern.negated {
+            "not ignored, re-included by"
+        } else {
+            "ignored by"
+        };
+        let block = match line_blocks.get(pattern.line - 1).copied().flatten() {
+            Some(name) => format!(" ({name} block)"),
+            None => String::new(),
+        };
+        println!(
+            "{path}: {verdict} {label}:{} \"{}\"{block}",
+            pattern.line, pattern.text
+        );
+    }
+    Ok(())
+}
+
+/// Merge templates into `existing` content as `gig --append` would, or
+/// generate fresh content like `gig <languages> -`.
+fn generate(languages: &[String], existing: Option<&str>) -> Result<String, json::Error> {
+    let languages = config()
+        .and_then(|c| c.expand(languages))
+        .code(ErrorCode::Config)?;
+    let mut templates: Vec<(&str, &str)> = Vec::new();
+    for lang in &languages {
+        templates.push(resolve_template(lang).code(ErrorCode::UnknownTemplate)?);
+    }
+    blocks::merge_blocks(existing, &templates).code(ErrorCode::InvalidFile)
+}
+
+/// List what adding templates to a directory's .gitignore would newly ignore,
+/// grouped by the line responsible.
+fn run_preview(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let after = split_at_dashes(args);
+    let show_tracked = args.contains("--tracked");
+    let mut free = positionals(args, after, 2)?.into_iter();
+    let languages = free
+        .next()
+        .ok_or(LANG_REQUIRED_ERR)
+        .map_err(String::from)
+        .and_then(|l| parse_languages(&l))
+        .code(ErrorCode::Usage)?;
+    let dir = free
+        .next()
+        .map_or_else(|| PathBuf::from("."), PathBuf::from);
+
+    let gitignore = dir.join(DEFAULT_OUTPUT);
+    let existing = read_existing_file(&gitignore).code(ErrorCode::Io)?;
+    let new_root =
+        generate(&languages, existing.as_deref()).map_err(|e| e.context(gitignore.display()))?;
+    let tracked: HashSet<String> = if show_tracked {
+        git::tracked_files(&dir)
+            .code(ErrorCode::Io)?
+            .into_iter()
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let result = preview::preview(&dir, &new_root, &tracked).code(ErrorCode::Io)?;
+    let line_blocks = blocks::line_blocks(&new_root);
+    if result.ignored.is_empty() {
+        println!("nothing in {} would be newly ignored", dir.display());
+    } else {
+        print_hits(&result.ignored, &line_blocks);
+    }
+    if !result.tracked.is_empty() {
+        println!("\ntracked files matching the new patterns (git keeps tracking them):");
+        print_hits(&result.tracked, &line_blocks);
+    }
+    Ok(())
+}
+
+/// Report problems in an existing ignore file, and with `--fix` remove the
+/// ones that can be fixed without changing what it ignores.
+fn run_lint(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let after = split_at_dashes(args);
+    let fix = args.contains("--fix");
+    let mode = parse_output_mode(args).code(ErrorCode::Usage)?;
+    let path = single_file(positionals(args, after, 1)?);
+    let content = read_existing(&path)?;
+
+    let mut findings = lint::lint(&content);
+    if fix {
+        let fixed = lint::apply_fixes(&content, &findings);
+        emit_output(&path, &fixed, true, mode).code(ErrorCode::Io)?;
+        let count = findings.iter().filter(|f| f.fix.is_some()).count();
+        eprintln!("fixed {count} problems in {}", path.display());
+        findings = lint::lint(&fixed);
+    }
+
+    // With --fix the file may be on stdout, so report on stderr
+    let mut errors = 0;
+    for finding in &findings {
+        let line = format!(
+            "{}:{}: {}: {}",
+            path.display(),
+            finding.line,
+            finding.severity,
+            finding.message
+        );
+        if fix {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+        if finding.severity == lint::Severity::Error {
+            errors += 1;
+        }
+    }
+
+    let warnings = findings.len() - errors;
+    let fixable = findings.iter().filter(|f| f.fix.is_some()).count();
+    if !fix && fixable > 0 {
+        eprintln!("{fixable} of these can be fixed with 'gig lint --fix'");
+    }
+    if errors > 0 {
+        return Err(json::Error::new(
+            ErrorCode::Failed,
+            format!("{}: {errors} errors, {warnings} warnings", path.display()),
+        ));
+    }
+    if warnings > 0 {
+        eprintln!("{}: {warnings} warnings", path.display());
+    }
+    Ok(())
+}
+
+/// Rewrite an existing ignore file in canonical form, or with `--check`
+/// only report whether it already is.
+fn run_fmt(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let after = split_at_dashes(args);
+    let check = args.contains("--check");
+    let sort = args.contains("--sort");
+    let mode = parse_output_mode(args).code(ErrorCode::Usage)?;
+    let path = single_file(positionals(args, after, 1)?);
+    let content = read_existing(&path)?;
+    let formatted = format::format(&content, sort)
+        .code(ErrorCode::InvalidFile)
+        .map_err(|e| e.context(path.display()))?;
+
+    if !check {
+        return emit_output(&path, &formatted, true, mode).code(ErrorCode::Io);
+    }
+    if formatted == content {
+        println!("{}: formatted", path.display());
+        return Ok(());
+    }
+    println!("{}: not formatted", path.display());
+    if mode == OutputMode::Diff {
+        let label = path.display().to_string();
+        print!(
+            "{}",
+            diff::unified_diff(&content, &formatted, &label, &label)
+        );
+    }
+    Err(json::Error::new(
+        ErrorCode::Failed,
+        format!("{} is not formatted; run 'gig fmt'", path.display()),
+    ))
+}
+
+/// Report where a pattern in a generated file came from: its block, the
+/// templates whose copy was deduplicated away, and lines that cover it.
+fn run_explain(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    const USAGE: &str = "usage: gig explain <file> <pattern>";
+    let after = split_at_dashes(args);
+    let [path, pattern] = <[String; 2]>::try_from(positionals(args, after, 2)?)
+        .map_err(|_| json::Error::new(ErrorCode::Usage, USAGE))?;
+    let path = PathBuf::from(path);
+    let pattern = pattern.trim();
+    let content = read_existing(&path)?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let line_blocks = blocks::line_blocks(&content);
+    let source = |i: usize| match line_blocks[i] {
+        Some(block) => format!("the {block} template"),
+        None => "a hand-written line".to_string(),
+    };
+
+    let mut found = false;
+    for (i, line) in lines.iter().enumerate() {
+        let Some(text) = blocks::pattern_of(line) else {
+            continue;
+        };
+        if text == pattern {
+            println!(
+                "{}:{}: \"{text}\" from {}",
+                path.display(),
+                i + 1,
+                source(i)
+            );
+            // Recorded by --annotate, or else worked out from the templates
+            let also = match i
+                .checked_sub(1)
+                .and_then(|prev| blocks::annotation_of(lines[prev]))
+            {
+                Some(names) => names.to_string(),
+                None => repeated_in(&lines, &line_blocks, line_blocks[i], text).join(", "),
+            };
+            if !also.is_empty() {
+                println!("  also in {also}");
+            }
+            found = true;
+        } else if normalize::covers(text, pattern) {
+            println!(
+                "{}:{}: \"{text}\" from {} covers \"{pattern}\"",
+                path.display(),
+                i + 1,
+                source(i)
+            );
+            found = true;
+        }
+    }
+
+    if !found {
+        return Err(json::Error::new(
+            ErrorCode::Failed,
+            format!("\"{pattern}\" is not in {}", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Blocks in a file, other than `own`, whose template has `pattern` even
+/// though the block doesn't: their copy was deduplicated away.
+fn repeated_in<'a>(
+    lines: &[&str],
+    line_blocks: &[Option<&'a str>],
+    own: Option<&str>,
+    pattern: &str,
+) -> Vec<&'a str> {
+    let mut names: Vec<&str> = line_blocks.iter().flatten().copied().collect();
+    names.dedup();
+    names
+        .into_iter()
+        .filter(|&name| Some(name) != own)
+        .filter(|&name| {
+            let in_block = lines.iter().zip(line_blocks).any(|(line, block)| {
+                *block == Some(name) && blocks::pattern_of(line) == Some(pattern)
+            });
+            !in_block
+                && get_template(name)
+                    .is_ok_and(|t| t.lines().any(|l| blocks::pattern_of(l) == Some(pattern)))
+        })
+        .collect()
+}
+
+/// Print preview hits grouped by the .gitignore line that ignores them.
+fn print_hits(hits: &[preview::Hit], line_blocks: &[Option<&str>]) {
+    let mut groups: Vec<(usize, &str, Vec<&preview::Hit>)> = Vec::new();
+    for hit in hits {
+        match groups.iter_mut().find(|(line, _, _)| *line == hit.line) {
+            Some((_, _, group)) => group.push(hit),
+            None => groups.push((hit.line, &hit.pattern, vec![hit])),
+        }
+    }
+    groups.sort_by_key(|(line, _, _)| *line);
+
+    for (line, pattern, group) in groups {
+        let source = line_blocks
+            .get(line - 1)
+            .copied()
+            .flatten()
+            .unwrap_or(DEFAULT_OUTPUT);
+        println!("{source}: {pattern} (line {line})");
+        for hit in group {
+            match hit.files {
+                0 => println!("  {}", hit.path),
+                1 => println!("  {} (1 file)", hit.path),
+                n => println!("  {} ({n} files)", hit.path),
+            }
+        }
+    }
+}
+
+/// Search templates and print each match with its scope and matching lines.
+fn run_search(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let term: String = args
+        .opt_free_from_str()
+        .map_err(|e| e.to_string())
+        .and_then(|t| t.ok_or("search term required (e.g., gig search .terraform)".to_string()))
+        .code(ErrorCode::Usage)?;
 
-    // Merge templates (prepend existing content so its patterns win dedup)
-    let content = match &existing_content {
-        Some(existing) => {
-            let mut all_templates: Vec<&str> = vec![existing.as_str()];
-            all_templates.extend(templates);
-            merge_templates(&all_templates)
+    let index = &*INDEX;
+    let matches = search::search(&term, index.iter().map(|(k, t)| (k.as_str(), t.content)));
+    if matches.is_empty() {
+        return Err(json::Error::new(
+            ErrorCode::Failed,
+            format!("no templates match \"{term}\""),
+        ));
+    }
+
+    for m in matches {
+        println!("{} ({})", m.key, m.scope);
+        for (lineno, line) in m.lines {
+            println!("  {lineno}: {line}");
         }
-        None => merge_templates(&templates),
+    }
+    Ok(())
+}
+
+/// Print one template with its metadata, through a pager when stdout is a
+/// terminal.
+///
+/// With `json`, prints the content and metadata as one JSON object instead.
+fn run_show(args: &mut pico_args::Arguments, json: bool) -> Result<(), json::Error> {
+    const USAGE: &str = "template name required (e.g., gig show python)";
+    let after = split_at_dashes(args);
+    let no_pager = args.contains("--no-pager");
+    let name = positionals(args, after, 1)?
+        .pop()
+        .ok_or_else(|| json::Error::new(ErrorCode::Usage, USAGE))?;
+
+    let index = &*INDEX;
+    let (key, _) = resolve_template(&name).code(ErrorCode::UnknownTemplate)?;
+    let template = &index[key];
+    let keys = index.keys().map(String::as_str);
+
+    if json {
+        let mut fields = template_fields(key, template);
+        fields.extend([
+            ("lines", template.content.lines().count().into()),
+            ("patterns", count_patterns(template.content).into()),
+            ("related", related_templates(key, keys).into()),
+            ("content", template.content.into()),
+        ]);
+        println!("{}", json::Value::Object(fields));
+        return Ok(());
+    }
+
+    let text = describe_template(key, template, keys);
+    if no_pager || !std::io::stdout().is_terminal() {
+        print!("{text}");
+        return Ok(());
+    }
+    page(&text).code(ErrorCode::Io)
+}
+
+/// The `--json` description of a template shared by `--list` and `show`.
+fn template_fields(key: &str, template: &Template) -> Vec<(&'static str, json::Value)> {
+    let (scope, subcategory) = template_scope(key);
+    let file_name = template.upstream.rsplit('/').next().unwrap_or_default();
+    let name = file_name
+        .strip_suffix(GITIGNORE_SUFFIX)
+        .unwrap_or(file_name);
+    vec![
+        ("key", key.into()),
+        ("name", name.into()),
+        ("scope", scope.into()),
+        ("subcategory", subcategory.into()),
+        ("source", template.source.kind().into()),
+        (
+            "path",
+            template
+                .source
+                .path()
+                .map(|p| p.display().to_string())
+                .into(),
+        ),
+        ("upstream", template.upstream.as_str().into()),
+    ]
+}
+
+/// A template preceded by comment lines giving its key, scope, upstream
+/// path, source, size and the other templates in its community subcategory.
+fn describe_template<'a>(
+    key: &str,
+    template: &Template,
+    keys: impl Iterator<Item = &'a str>,
+) -> String {
+    let content = template.content;
+    let patterns = count_patterns(content);
+    let source = match template.source.path() {
+        Some(path) => format!("{} ({})", template.source.kind(), path.display()),
+        None => template.source.kind().to_string(),
+    };
+
+    let mut text = format!(
+        "# template: {key}\n# scope: {}\n# upstream: {}\n# source: {source}\n# lines: {}, patterns: {patterns}\n",
+        scope_label(key),
+        template.upstream,
+        content.lines().count(),
+    );
+    let related = related_templates(key, keys);
+    if !related.is_empty() {
+        text.push_str(&format!("# related: {}\n", related.join(", ")));
+    }
+    text.push('\n');
+    text.push_str(content);
+    if !content.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// Other templates in the same community subcategory as `key`, sorted.
+fn related_templates<'a>(key: &str, keys: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let ("community", Some(sub)) = template_scope(key) else {
+        return Vec::new();
     };
+    let mut related: Vec<&str> = keys
+        .filter(|&k| k != key && template_scope(k) == ("community", Some(sub)))
+        .collect();
+    related.sort_unstable();
+    related
+}
 
-    if let Err(e) = write_output(&output, &content, append_mode) {
-        eprintln!("error: {e}");
-        process::exit(1);
+/// Show `text` in `$PAGER`, or `less` if it isn't set. An empty `$PAGER`, or
+/// one that can't be started, prints it directly.
+fn page(text: &str) -> Result<(), String> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -FRX".to_string());
+    let mut words = pager.split_whitespace();
+    let child = words.next().and_then(|program| {
+        process::Command::new(program)
+            .args(words)
+            .stdin(process::Stdio::piped())
+            .spawn()
+            .ok()
+    });
+    let Some(mut child) = child else {
+        print!("{text}");
+        return Ok(());
+    };
+
+    // Quitting the pager early closes the pipe, which isn't an error
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child
+        .wait()
+        .map_err(|e| format!("failed to run pager \"{pager}\": {e}"))?;
+    Ok(())
+}
+
+/// Fetch templates into the cache from the given source, the config's
+/// `[update] source`, or upstream.
+fn run_update(args: &mut pico_args::Arguments, _json: bool) -> Result<(), json::Error> {
+    let source: Option<String> = args
+        .opt_free_from_str()
+        .map_err(|e| e.to_string())
+        .code(ErrorCode::Usage)?;
+    let source = match source {
+        Some(source) => source,
+        None => config()
+            .code(ErrorCode::Config)?
+            .update_source
+            .clone()
+            .unwrap_or_else(|| update::DEFAULT_SOURCE.to_string()),
+    };
+    let cache = update::cache_dir()
+        .ok_or("cannot locate a cache directory; set XDG_CACHE_HOME")
+        .code(ErrorCode::Io)?;
+
+    let previous = update::CacheInfo::load(&cache).code(ErrorCode::Io)?;
+    let (info, count) = update::update(&source, &cache).code(ErrorCode::Io)?;
+    println!("updated {count} templates from {}", info.describe());
+    if let Some(previous) = previous {
+        println!(
+            "replaced templates from {}, fetched {}",
+            previous.describe(),
+            previous.fetched_at
+        );
     }
+    println!("cached in {}", update::templates_dir(&cache).display());
+    Ok(())
 }
 
 /// Parse comma-separated language list, validating no empty segments.
@@ -124,34 +1491,47 @@ fn parse_languages(input: &str) -> Result<Vec<String>, String> {
     Ok(languages)
 }
 
-/// Merge multiple templates, deduplicating patterns but preserving comments and blanks.
-fn merge_templates(templates: &[&str]) -> String {
-    let mut seen_patterns: HashSet<&str> = HashSet::new();
-    let mut output = String::new();
-
-    for template in templates {
-        for line in template.lines() {
-            let trimmed = line.trim();
+fn parse_dedupe(args: &mut pico_args::Arguments) -> Result<Dedupe, String> {
+    let value: Option<String> = args
+        .opt_value_from_str("--dedupe")
+        .map_err(|e| e.to_string())?;
+    match value.as_deref() {
+        None | Some("exact") => Ok(Dedupe::Exact),
+        Some("semantic") => Ok(Dedupe::Semantic),
+        Some(other) => Err(format!(
+            "unknown --dedupe mode \"{other}\" (expected exact or semantic)"
+        )),
+    }
+}
 
-            // Comments and blank lines are always included
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                output.push_str(line);
-                output.push('\n');
-                continue;
-            }
+fn parse_format(args: &mut pico_args::Arguments) -> Result<Format, String> {
+    let value: Option<String> = args
+        .opt_value_from_str("--format")
+        .map_err(|e| e.to_string())?;
+    value.map_or(Ok(Format::Gitignore), |name| Format::parse(&name))
+}
 
-            // Patterns are deduplicated by exact match
-            if seen_patterns.insert(trimmed) {
-                output.push_str(line);
-                output.push('\n');
-            }
-        }
+/// The excludes file named by `--target`, if given.
+fn parse_target(args: &mut pico_args::Arguments) -> Result<Option<PathBuf>, String> {
+    let value: Option<String> = args
+        .opt_value_from_str("--target")
+        .map_err(|e| e.to_string())?;
+    match value.as_deref() {
+        None => Ok(None),
+        Some("exclude") => git::exclude_file(Path::new(".")).map(Some),
+        Some("global") => git::global_excludes_file().map(Some),
+        Some(other) => Err(format!(
+            "unknown --target \"{other}\" (expected exclude or global)"
+        )),
     }
-
-    output
 }
 
-fn parse_args(args: &mut pico_args::Arguments) -> Result<(Vec<String>, PathBuf), String> {
+fn parse_args(
+    args: &mut pico_args::Arguments,
+    default_output: impl AsRef<Path>,
+) -> Result<(Vec<String>, PathBuf), String> {
+    let to_stdout = args.contains("--stdout");
+
     // First positional: languages (required)
     let languages_arg: Option<String> = args.opt_free_from_str().map_err(|e| e.to_string())?;
 
@@ -159,57 +1539,506 @@ fn parse_args(args: &mut pico_args::Arguments) -> Result<(Vec<String>, PathBuf),
     let languages = parse_languages(&languages_str)?;
 
     // Second positional: output path (optional)
-    let output: PathBuf = args
-        .opt_free_from_str()
-        .map_err(|e| e.to_string())?
-        .unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT));
+    let output = parse_output(args, to_stdout, default_output)?;
+
+    Ok((languages, output))
+}
+
+/// Parse the optional output path positional, honouring --stdout.
+fn parse_output(
+    args: &mut pico_args::Arguments,
+    to_stdout: bool,
+    default: impl AsRef<Path>,
+) -> Result<PathBuf, String> {
+    let output: Option<PathBuf> = args.opt_free_from_str().map_err(|e| e.to_string())?;
+    output_path(output, to_stdout, default)
+}
+
+/// The output path given, if any, honouring --stdout.
+fn output_path(
+    output: Option<PathBuf>,
+    to_stdout: bool,
+    default: impl AsRef<Path>,
+) -> Result<PathBuf, String> {
+    match (output, to_stdout) {
+        (Some(path), true) if !is_stdout(&path) => {
+            Err("--stdout cannot be combined with an output path".to_string())
+        }
+        (_, true) => Ok(PathBuf::from(STDOUT_PATH)),
+        (Some(path), false) => Ok(path),
+        (None, false) => Ok(default.as_ref().to_path_buf()),
+    }
+}
+
+/// Parse arguments for --detect: an optional output path, with languages
+/// detected from marker files in the output's directory.
+fn parse_detect_args(
+    args: &mut pico_args::Arguments,
+    default_output: impl AsRef<Path>,
+) -> Result<(Vec<String>, PathBuf), String> {
+    let to_stdout = args.contains("--stdout");
+    let output = parse_output(args, to_stdout, default_output)?;
+
+    let dir = match output.parent() {
+        Some(parent) if !is_stdout(&output) && !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let languages = detect_languages(dir)?;
+
+    Ok((languages, output))
+}
+
+/// Detect templates for the project in `dir` and report them on stderr.
+///
+/// Markers whose template isn't in the index are skipped.
+fn detect_languages(dir: &Path) -> Result<Vec<String>, String> {
+    let mut languages: Vec<String> = Vec::new();
+    for detection in detect::detect(dir, detect::MARKERS)? {
+        let Ok((key, _)) = resolve_template(detection.template) else {
+            continue;
+        };
+        if !languages.iter().any(|l| l == key) {
+            eprintln!("detected {key} ({})", detection.found);
+            languages.push(key.to_string());
+        }
+    }
+
+    if languages.is_empty() {
+        return Err(format!("no known project files found in {}", dir.display()));
+    }
+    Ok(languages)
+}
+
+/// Whether an output path means standard output/input rather than a file.
+fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == STDOUT_PATH
+}
+
+/// Where a template in the index came from.
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    /// Baked into the binary at build time.
+    Embedded,
+    /// Loaded from this file in the `gig update` cache.
+    Cached(PathBuf),
+    /// Loaded from this file in a local template directory.
+    Local(PathBuf),
+}
+
+impl Source {
+    /// `embedded`, `cache` or `local`.
+    fn kind(&self) -> &'static str {
+        match self {
+            Source::Embedded => "embedded",
+            Source::Cached(_) => "cache",
+            Source::Local(_) => "local",
+        }
+    }
+
+    /// The file the template was loaded from, unless it is embedded.
+    fn path(&self) -> Option<&Path> {
+        match self {
+            Source::Embedded => None,
+            Source::Cached(path) | Source::Local(path) => Some(path),
+        }
+    }
+}
+
+/// A template in the index.
+#[derive(Debug, Clone)]
+struct Template {
+    content: &'static str,
+    source: Source,
+    /// Path relative to the templates root, e.g. `community/Golang/Hugo.gitignore`.
+    upstream: String,
+}
+
+/// Build the index from this machine's templates: the `gig update` cache,
+/// the embedded set and the local directories from `template_dirs`.
+fn build_index() -> HashMap<String, Template> {
+    let cached = update::cache_dir()
+        .map(|cache| load_templates(&update::templates_dir(&cache), Source::Cached))
+        .unwrap_or_default();
+    let local = template_dirs()
+        .iter()
+        .map(|dir| load_templates(dir, Source::Local))
+        .collect();
+    index_from(embedded_templates(), cached, local)
+}
+
+/// An index mapping lowercase language names to their templates.
+///
+/// The base set is the `cached` templates if there are any, else the
+/// `embedded` ones. `local` templates, one list per directory, shadow base
+/// ones with the same key; among local directories, the first one listed wins.
+fn index_from(
+    embedded: HashMap<String, Template>,
+    cached: Vec<(String, Template)>,
+    local: Vec<Vec<(String, Template)>>,
+) -> HashMap<String, Template> {
+    let mut index: HashMap<String, Template> = if cached.is_empty() {
+        embedded
+    } else {
+        cached.into_iter().collect()
+    };
+
+    for templates in local {
+        for (key, template) in templates {
+            let shadowed_local = index
+                .get(&key)
+                .is_some_and(|t| matches!(t.source, Source::Local(_)));
+            if !shadowed_local {
+                index.insert(key, template);
+            }
+        }
+    }
+
+    index
+}
+
+/// The templates baked into the binary, keyed by lowercase name.
+fn embedded_templates() -> HashMap<String, Template> {
+    let upstream: HashMap<&str, &str> = UPSTREAM
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    TEMPLATES
+        .files()
+        .filter_map(|file| {
+            let name = file.path().file_name()?.to_str()?;
+            let lang = name
+                .strip_suffix(GITIGNORE_SUFFIX)
+                .filter(|s| !s.is_empty())?;
+            let content = file.contents_utf8()?;
+            let template = Template {
+                content,
+                source: Source::Embedded,
+                upstream: upstream.get(name).unwrap_or(&name).to_string(),
+            };
+            Some((lang.to_lowercase(), template))
+        })
+        .collect()
+}
+
+/// Local template directories: `GIG_TEMPLATE_PATH` entries first, then the
+/// config file's `template_path`.
+fn template_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::env::var_os(TEMPLATE_PATH_ENV)
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    // A broken config is reported by the commands that read it
+    if let Ok(config) = config() {
+        dirs.extend(config.template_path.iter().cloned());
+    }
+    dirs.retain(|d| !d.as_os_str().is_empty());
+    dirs
+}
+
+/// Load the `.gitignore` templates under `dir`, keyed like embedded ones and
+/// tagged with `source` for the file each came from.
+///
+/// A missing directory yields nothing; unreadable files are skipped with a warning.
+fn load_templates(dir: &Path, source: fn(PathBuf) -> Source) -> Vec<(String, Template)> {
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+
+    let mut files = Vec::new();
+    if let Err(e) = layout::collect_templates(dir, &mut files) {
+        eprintln!(
+            "warning: failed to read templates in {}: {e}",
+            dir.display()
+        );
+    }
+
+    files
+        .into_iter()
+        .filter_map(|(path, bare_name)| {
+            let rel = path.strip_prefix(dir).ok()?;
+            let dest_name = layout::compute_dest_name(rel, &bare_name);
+            let key = dest_name.strip_suffix(GITIGNORE_SUFFIX)?.to_lowercase();
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    eprintln!("warning: failed to read {}: {e}", path.display());
+                    return None;
+                }
+            };
+            // The index lives for the whole run, like the embedded templates
+            let template = Template {
+                content: Box::leak(content.into_boxed_str()),
+                upstream: layout::upstream_path(rel),
+                source: source(path),
+            };
+            Some((key, template))
+        })
+        .collect()
+}
+
+/// Get template content for a language (see `resolve_template`).
+fn get_template(lang: &str) -> Result<&'static str, String> {
+    resolve_template(lang).map(|(_, content)| content)
+}
+
+/// Resolve a language to its index key and template content.
+///
+/// Exact (case-insensitive) matches win, so top-level templates keep priority.
+/// Otherwise a name matching the last dotted component of exactly one nested
+/// key resolves to it, e.g. `hugo` to `community.golang.hugo`.
+fn resolve_template(lang: &str) -> Result<(&'static str, &'static str), String> {
+    let index: &'static HashMap<String, Template> = &INDEX;
+    let key = lang.to_lowercase();
+
+    if let Some((k, template)) = index.get_key_value(&key) {
+        return Ok((k.as_str(), template.content));
+    }
+
+    let suffix = format!(".{key}");
+    let mut matches: Vec<(&'static str, &'static str)> = index
+        .iter()
+        .filter(|(k, _)| k.ends_with(&suffix))
+        .map(|(k, template)| (k.as_str(), template.content))
+        .collect();
+
+    match matches.len() {
+        1 => return Ok(matches[0]),
+        0 => {}
+        _ => {
+            matches.sort_unstable();
+            let names: Vec<&str> = matches.iter().map(|(k, _)| *k).collect();
+            return Err(format!(
+                "ambiguous language \"{lang}\"; matches: {}",
+                names.join(", ")
+            ));
+        }
+    }
+
+    let suggestions = suggest::suggest(&key, index.keys().map(String::as_str), MAX_SUGGESTIONS);
+    if suggestions.is_empty() {
+        Err(format!("no template found for language \"{lang}\""))
+    } else {
+        Err(format!(
+            "no template found for language \"{lang}\"; did you mean: {}?",
+            suggestions.join(", ")
+        ))
+    }
+}
+
+/// Get the sorted list of available template keys.
+fn get_language_list() -> Vec<String> {
+    let index = &*INDEX;
+    let mut langs: Vec<_> = index.keys().cloned().collect();
+    langs.sort_unstable();
+    langs
+}
+
+/// List all available languages.
+fn list_languages() -> Result<(), String> {
+    let config = config()?;
+
+    let index = &*INDEX;
+    for lang in get_language_list() {
+        println!("{}", list_entry(&lang, &index[&lang].source));
+    }
+
+    // Presets and aliases from the user config
+    for (name, templates) in &config.presets {
+        println!("@{name} (preset: {})", templates.join(", "));
+    }
+    for (name, target) in &config.aliases {
+        println!("{name} (alias: {target})");
+    }
+    Ok(())
+}
+
+/// A template's line in `--list`: its key, marked with where it came from
+/// unless it is embedded.
+fn list_entry(key: &str, source: &Source) -> String {
+    match source {
+        Source::Embedded => key.to_string(),
+        Source::Cached(_) => format!("{key} (cached)"),
+        Source::Local(path) => format!("{key} (local: {})", path.display()),
+    }
+}
+
+/// `--list --json`: every template with its metadata, then the presets and
+/// aliases from the user config.
+fn list_languages_json() -> Result<(), String> {
+    let config = config()?;
+
+    let index = &*INDEX;
+    let templates: Vec<json::Value> = get_language_list()
+        .iter()
+        .map(|key| json::Value::Object(template_fields(key, &index[key])))
+        .collect();
+    let presets: Vec<json::Value> = config
+        .presets
+        .iter()
+        .map(|(name, templates)| {
+            json::Value::Object(vec![
+                ("name", name.as_str().into()),
+                ("templates", templates.clone().into()),
+            ])
+        })
+        .collect();
+    let aliases: Vec<json::Value> = config
+        .aliases
+        .iter()
+        .map(|(name, target)| {
+            json::Value::Object(vec![
+                ("name", name.as_str().into()),
+                ("target", target.as_str().into()),
+            ])
+        })
+        .collect();
+
+    let list = json::Value::Object(vec![
+        ("templates", templates.into()),
+        ("presets", presets.into()),
+        ("aliases", aliases.into()),
+    ]);
+    println!("{list}");
+    Ok(())
+}
+
+/// Take `--` and everything after it out of `args`: those arguments are
+/// positional even if they look like options.
+fn split_at_dashes(args: &mut pico_args::Arguments) -> Vec<OsString> {
+    let mut before = std::mem::replace(args, pico_args::Arguments::from_vec(Vec::new())).finish();
+    let after = match before.iter().position(|a| a.to_str() == Some("--")) {
+        Some(i) => before.split_off(i).split_off(1),
+        None => Vec::new(),
+    };
+    *args = pico_args::Arguments::from_vec(before);
+    after
+}
+
+/// The positional arguments left once a subcommand's options are parsed,
+/// followed by `after` (see `split_at_dashes`). Unknown options and more than
+/// `max` arguments are usage errors.
+fn positionals(
+    args: &mut pico_args::Arguments,
+    after: Vec<OsString>,
+    max: usize,
+) -> Result<Vec<String>, json::Error> {
+    let before = std::mem::replace(args, pico_args::Arguments::from_vec(Vec::new())).finish();
+    let free = free_paths(before, after).code(ErrorCode::Usage)?;
+    if let Some(extra) = free.get(max) {
+        return Err(json::Error::new(
+            ErrorCode::Usage,
+            format!("unexpected argument '{extra}'"),
+        ));
+    }
+    Ok(free)
+}
+
+/// The paths left once options are parsed, followed by those after `--`.
+/// Anything else before `--` that starts with `-` (other than `-` itself,
+/// for stdin or stdout) is an unknown option.
+fn free_paths(before: Vec<OsString>, after: Vec<OsString>) -> Result<Vec<String>, String> {
+    if let Some(option) = before
+        .iter()
+        .find(|a| a.to_string_lossy().starts_with('-') && *a != STDOUT_PATH)
+    {
+        return Err(format!(
+            "unknown option '{}' (put paths starting with - after --)",
+            option.to_string_lossy()
+        ));
+    }
+    Ok(before
+        .into_iter()
+        .chain(after)
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// The provenance comment written with `--header`.
+fn header_line() -> String {
+    format!(
+        "{}{} from {}",
+        blocks::HEADER_PREFIX,
+        env!("CARGO_PKG_VERSION"),
+        template_revision()
+    )
+}
+
+/// Describe the base template set in use: the `gig update` cache if it
+/// replaced the embedded templates, else the embedded templates' revision.
+fn template_revision() -> String {
+    let cached = INDEX
+        .values()
+        .any(|t| matches!(t.source, Source::Cached(_)));
+    let info = update::cache_dir().and_then(|cache| update::CacheInfo::load(&cache).ok()?);
+    if cached && let Some(info) = info {
+        return format!(
+            "{} (fetched {})",
+            info.describe(),
+            date_of(&info.fetched_at)
+        );
+    }
 
-    Ok((languages, output))
+    match (TEMPLATES_COMMIT, TEMPLATES_DATE) {
+        (Some(commit), Some(date)) => format!(
+            "github/gitignore at {} ({})",
+            update::short_commit(commit),
+            date_of(date)
+        ),
+        _ => "github/gitignore (unknown revision)".to_string(),
+    }
 }
 
-/// Build an index mapping lowercase language names to their template content.
-fn build_index() -> HashMap<String, &'static str> {
-    TEMPLATES
-        .files()
-        .filter_map(|file| {
-            let name = file.path().file_name()?.to_str()?;
-            let lang = name
-                .strip_suffix(GITIGNORE_SUFFIX)
-                .filter(|s| !s.is_empty())?;
-            let content = file.contents_utf8()?;
-            Some((lang.to_lowercase(), content))
-        })
-        .collect()
+/// The date part of an RFC 3339 timestamp.
+fn date_of(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
 }
 
-/// Get template content for a language (case-insensitive exact match).
-fn get_template(lang: &str) -> Result<&'static str, String> {
-    let index = &*INDEX;
-    let key = lang.to_lowercase();
+/// Print where each template set comes from, for `--version --verbose`.
+fn print_template_versions() {
+    match (TEMPLATES_COMMIT, TEMPLATES_DATE) {
+        (Some(commit), Some(date)) => {
+            println!("embedded templates: github/gitignore {commit} ({date})")
+        }
+        _ => println!("embedded templates: github/gitignore (unknown revision)"),
+    }
 
-    index
-        .get(&key)
-        .copied()
-        .ok_or_else(|| format!("no template found for language \"{lang}\""))
-}
+    let cache = update::cache_dir();
+    match cache.as_deref().map(update::CacheInfo::load) {
+        Some(Ok(Some(info))) => {
+            let used = INDEX
+                .values()
+                .any(|t| matches!(t.source, Source::Cached(_)));
+            println!(
+                "cached templates: {} {}, fetched {}{}",
+                info.source,
+                info.commit.as_deref().unwrap_or("(unknown revision)"),
+                info.fetched_at,
+                if used { "" } else { " (not in use)" }
+            );
+        }
+        Some(Err(e)) => println!("cached templates: {e}"),
+        _ => {}
+    }
 
-/// Get the sorted list of available template keys.
-fn get_language_list() -> Vec<String> {
-    let index = &*INDEX;
-    let mut langs: Vec<_> = index.keys().cloned().collect();
-    langs.sort_unstable();
-    langs
+    for dir in template_dirs() {
+        println!("local templates: {}", dir.display());
+    }
 }
 
-/// List all available languages.
-fn list_languages() {
-    for lang in get_language_list() {
-        println!("{lang}");
-    }
+/// The user config, loaded on first use.
+fn config() -> Result<&'static Config, String> {
+    CONFIG.as_ref().map_err(Clone::clone)
 }
 
 /// Write content to a file, refusing to overwrite unless allow_overwrite is true.
+///
+/// A path of `-` writes to stdout.
 fn write_output(path: &Path, content: &str, allow_overwrite: bool) -> Result<(), String> {
+    if is_stdout(path) {
+        return std::io::stdout()
+            .write_all(content.as_bytes())
+            .map_err(|e| e.to_string());
+    }
+
     let mut opts = OpenOptions::new();
     opts.write(true);
     if allow_overwrite {
@@ -219,10 +2048,7 @@ fn write_output(path: &Path, content: &str, allow_overwrite: bool) -> Result<(),
     }
     let mut file = opts.open(path).map_err(|e| {
         if e.kind() == ErrorKind::AlreadyExists {
-            format!(
-                "file {} already exists; use --append to merge or remove it first",
-                path.display()
-            )
+            already_exists_error(path)
         } else {
             e.to_string()
         }
@@ -231,8 +2057,40 @@ fn write_output(path: &Path, content: &str, allow_overwrite: bool) -> Result<(),
         .map_err(|e| e.to_string())
 }
 
+fn already_exists_error(path: &Path) -> String {
+    format!(
+        "file {} already exists; use --append to merge or remove it first",
+        path.display()
+    )
+}
+
+/// The file a subcommand works on: the one positional argument given, or
+/// `.gitignore`.
+fn single_file(free: Vec<String>) -> PathBuf {
+    free.into_iter()
+        .next()
+        .map_or_else(|| PathBuf::from(DEFAULT_OUTPUT), PathBuf::from)
+}
+
+/// The content of a file a subcommand needs to exist.
+fn read_existing(path: &Path) -> Result<String, json::Error> {
+    read_existing_file(path)
+        .and_then(|c| c.ok_or_else(|| format!("file {} does not exist", path.display())))
+        .code(ErrorCode::Io)
+}
+
 /// Read existing file content, returning None if the file doesn't exist.
+///
+/// A path of `-` reads from stdin.
 fn read_existing_file(path: &Path) -> Result<Option<String>, String> {
+    if is_stdout(path) {
+        let mut content = String::new();
+        return std::io::stdin()
+            .read_to_string(&mut content)
+            .map(|_| Some(content))
+            .map_err(|e| format!("failed to read stdin: {e}"));
+    }
+
     match std::fs::read_to_string(path) {
         Ok(content) => Ok(Some(content)),
         Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
@@ -269,6 +2127,233 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_load_templates_uses_scoped_keys() {
+        let dir = unique_dir("local_templates");
+        fs::create_dir_all(dir.join("Global")).unwrap();
+        fs::create_dir_all(dir.join("community/Internal")).unwrap();
+        fs::write(dir.join("Python.gitignore"), "# ours\n").unwrap();
+        fs::write(dir.join("Global/Editor.gitignore"), "*.ed\n").unwrap();
+        fs::write(dir.join("community/Internal/BuildTool.gitignore"), "out/\n").unwrap();
+
+        let mut templates = load_templates(&dir, Source::Local);
+        templates.sort_by(|a, b| a.0.cmp(&b.0));
+        let keys: Vec<&str> = templates.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec!["community.internal.buildtool", "global.editor", "python"]
+        );
+        assert_eq!(templates[2].1.content, "# ours\n");
+        assert_eq!(
+            templates[2].1.source,
+            Source::Local(dir.join("Python.gitignore"))
+        );
+        assert_eq!(
+            templates[0].1.upstream,
+            "community/Internal/BuildTool.gitignore"
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_load_templates_missing_dir() {
+        assert!(load_templates(Path::new("/nonexistent/gig/templates"), Source::Local).is_empty());
+    }
+
+    #[test]
+    fn test_index_embedded_source() {
+        let index = index_from(embedded_templates(), Vec::new(), Vec::new());
+        assert_eq!(index["python"].source, Source::Embedded);
+    }
+
+    fn template(content: &'static str, source: Source) -> Template {
+        Template {
+            content,
+            source,
+            upstream: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_templates_present() {
+        let index: HashMap<String, Template> = [
+            ("a", "*.a\nshared\n"),
+            ("b", "# B\n*.b\nshared\n"),
+            ("c", "*.c\n"),
+            ("d", "shared\n"),
+        ]
+        .into_iter()
+        .map(|(key, content)| (key.to_string(), template(content, Source::Embedded)))
+        .collect();
+
+        // A flat file: b's patterns are all there, c's aren't, and d only
+        // has what a has too
+        let flat = "*.a\nshared\n*.b\n";
+        assert_eq!(
+            templates_present(flat, &["a"], Format::Gitignore, &index),
+            vec!["b"]
+        );
+        // Every pattern of both b and c, next to a's block
+        let mixed = "# >>> gig: a\n*.a\nshared\n# <<< gig: a\n*.c\n*.b\nshared\n";
+        let mut present = templates_present(mixed, &["a"], Format::Gitignore, &index);
+        present.sort();
+        assert_eq!(present, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_index_from_cache_replaces_embedded() {
+        let cached = vec![(
+            "go".to_string(),
+            template("*.exe\n", Source::Cached(PathBuf::from("/c/Go.gitignore"))),
+        )];
+        let index = index_from(embedded_templates(), cached, Vec::new());
+        assert_eq!(index.len(), 1);
+        assert_eq!(index["go"].content, "*.exe\n");
+    }
+
+    #[test]
+    fn test_index_from_local_shadows_in_order() {
+        let first = vec![(
+            "python".to_string(),
+            template("# first\n", Source::Local(PathBuf::from("/a"))),
+        )];
+        let second = vec![
+            (
+                "python".to_string(),
+                template("# second\n", Source::Local(PathBuf::from("/b"))),
+            ),
+            (
+                "mine".to_string(),
+                template("out/\n", Source::Local(PathBuf::from("/b"))),
+            ),
+        ];
+        let index = index_from(embedded_templates(), Vec::new(), vec![first, second]);
+        assert_eq!(index["python"].content, "# first\n");
+        assert_eq!(index["mine"].content, "out/\n");
+        assert_eq!(index["go"].source, Source::Embedded);
+    }
+
+    #[test]
+    fn test_list_entry_marks_source() {
+        assert_eq!(list_entry("go", &Source::Embedded), "go");
+        assert_eq!(
+            list_entry("go", &Source::Cached(PathBuf::from("/c/Go.gitignore"))),
+            "go (cached)"
+        );
+        assert_eq!(
+            list_entry("go", &Source::Local(PathBuf::from("/t/Go.gitignore"))),
+            "go (local: /t/Go.gitignore)"
+        );
+    }
+
+    #[test]
+    fn test_index_embedded_upstream() {
+        let index = index_from(embedded_templates(), Vec::new(), Vec::new());
+        assert_eq!(index["python"].upstream, "Python.gitignore");
+        assert_eq!(index["global.macos"].upstream, "Global/macOS.gitignore");
+    }
+
+    #[test]
+    fn test_related_templates() {
+        let keys = [
+            "go",
+            "community.golang.revel",
+            "community.golang.hugo",
+            "community.javascript.vue",
+            "community.terraform",
+        ];
+        assert_eq!(
+            related_templates("community.golang.hugo", keys.into_iter()),
+            vec!["community.golang.revel"]
+        );
+        assert!(related_templates("go", keys.into_iter()).is_empty());
+        assert!(related_templates("community.terraform", keys.into_iter()).is_empty());
+    }
+
+    #[test]
+    fn test_describe_template() {
+        let template = Template {
+            content: "# Build\n/public/\n\n*.lock",
+            source: Source::Local(PathBuf::from("/t/community/Golang/Hugo.gitignore")),
+            upstream: "community/Golang/Hugo.gitignore".to_string(),
+        };
+        let keys = ["community.golang.hugo", "community.golang.revel"];
+        assert_eq!(
+            describe_template("community.golang.hugo", &template, keys.into_iter()),
+            "# template: community.golang.hugo\n\
+             # scope: community/golang\n\
+             # upstream: community/Golang/Hugo.gitignore\n\
+             # source: local (/t/community/Golang/Hugo.gitignore)\n\
+             # lines: 4, patterns: 2\n\
+             # related: community.golang.revel\n\
+             \n# Build\n/public/\n\n*.lock\n"
+        );
+    }
+
+    #[test]
+    fn test_template_fields() {
+        let template = Template {
+            content: "*.o\n",
+            source: Source::Local(PathBuf::from("/t/community/Golang/Hugo.gitignore")),
+            upstream: "community/Golang/Hugo.gitignore".to_string(),
+        };
+        assert_eq!(
+            json::Value::Object(template_fields("community.golang.hugo", &template)).to_string(),
+            r#"{"key":"community.golang.hugo","name":"Hugo","scope":"community","subcategory":"golang","source":"local","path":"/t/community/Golang/Hugo.gitignore","upstream":"community/Golang/Hugo.gitignore"}"#
+        );
+    }
+
+    #[test]
+    fn test_generate_summary_counts_deduplicated_patterns() {
+        let templates = vec![
+            ("c".to_string(), "# C\n*.o\n*.so\n".to_string()),
+            ("rust".to_string(), "target/\n*.so\n".to_string()),
+        ];
+        let named: Vec<(&str, &str)> = templates
+            .iter()
+            .map(|(n, c)| (n.as_str(), c.as_str()))
+            .collect();
+        let content = blocks::merge_blocks(Some("*.o\n"), &named).unwrap();
+        assert_eq!(
+            generate_summary(
+                Path::new(".gitignore"),
+                Format::Gitignore,
+                &templates,
+                Some("*.o\n"),
+                &content
+            )
+            .to_string(),
+            r#"{"output":".gitignore","format":"gitignore","templates":["c","rust"],"patterns_added":2,"patterns_deduplicated":2}"#
+        );
+    }
+
+    #[test]
+    fn test_generate_summary_counts_only_new_patterns_on_append() {
+        let templates = vec![
+            ("c".to_string(), "# C\n*.o\n*.so\n".to_string()),
+            ("rust".to_string(), "target/\n".to_string()),
+        ];
+        // The c block is already there from an earlier run
+        let existing = blocks::merge_blocks(Some("*.o\n"), &[("c", &templates[0].1)]).unwrap();
+        let named: Vec<(&str, &str)> = templates
+            .iter()
+            .map(|(n, c)| (n.as_str(), c.as_str()))
+            .collect();
+        let content = blocks::merge_blocks(Some(&existing), &named).unwrap();
+        assert_eq!(
+            generate_summary(
+                Path::new(".gitignore"),
+                Format::Gitignore,
+                &templates,
+                Some(&existing),
+                &content
+            )
+            .to_string(),
+            r#"{"output":".gitignore","format":"gitignore","templates":["c","rust"],"patterns_added":1,"patterns_deduplicated":1}"#
+        );
+    }
+
     #[test]
     fn test_get_template_exact_match() {
         let result = get_template("python");
@@ -292,6 +2377,19 @@ mod tests {
         assert!(result.unwrap_err().contains("no template found"));
     }
 
+    #[test]
+    fn test_get_template_not_found_suggests() {
+        let err = get_template("pyhton").unwrap_err();
+        assert!(err.contains("did you mean"), "got: {err}");
+        assert!(err.contains("python"), "got: {err}");
+    }
+
+    #[test]
+    fn test_get_template_not_found_suggests_nested() {
+        let err = get_template("vu").unwrap_err();
+        assert!(err.contains("community.javascript.vue"), "got: {err}");
+    }
+
     #[test]
     fn test_write_output_creates_file() {
         let dir = unique_dir("creates_file");
@@ -328,17 +2426,24 @@ mod tests {
     #[test]
     fn test_parse_args_single_language() {
         let mut args = pico_args::Arguments::from_vec(vec!["python".into()]);
-        let result = parse_args(&mut args);
+        let result = parse_args(&mut args, DEFAULT_OUTPUT);
         assert!(result.is_ok());
         let (langs, output) = result.unwrap();
         assert_eq!(langs, vec!["python".to_string()]);
         assert_eq!(output, PathBuf::from(".gitignore"));
     }
 
+    #[test]
+    fn test_parse_args_default_output_follows_format() {
+        let mut args = pico_args::Arguments::from_vec(vec!["python".into()]);
+        let (_, output) = parse_args(&mut args, Format::Dockerignore.file_name()).unwrap();
+        assert_eq!(output, PathBuf::from(".dockerignore"));
+    }
+
     #[test]
     fn test_parse_args_multiple_languages() {
         let mut args = pico_args::Arguments::from_vec(vec!["go,godot,emacs".into()]);
-        let result = parse_args(&mut args);
+        let result = parse_args(&mut args, DEFAULT_OUTPUT);
         assert!(result.is_ok());
         let (langs, output) = result.unwrap();
         assert_eq!(
@@ -352,24 +2457,77 @@ mod tests {
     fn test_parse_args_with_output_path() {
         let mut args =
             pico_args::Arguments::from_vec(vec!["rust".into(), "custom.gitignore".into()]);
-        let result = parse_args(&mut args);
+        let result = parse_args(&mut args, DEFAULT_OUTPUT);
         assert!(result.is_ok());
         let (langs, output) = result.unwrap();
         assert_eq!(langs, vec!["rust".to_string()]);
         assert_eq!(output, PathBuf::from("custom.gitignore"));
     }
 
+    #[test]
+    fn test_parse_args_dash_output() {
+        let mut args = pico_args::Arguments::from_vec(vec!["rust".into(), "-".into()]);
+        let (_, output) = parse_args(&mut args, DEFAULT_OUTPUT).unwrap();
+        assert!(is_stdout(&output));
+    }
+
+    #[test]
+    fn test_parse_args_stdout_flag() {
+        let mut args = pico_args::Arguments::from_vec(vec!["--stdout".into(), "rust".into()]);
+        let (langs, output) = parse_args(&mut args, DEFAULT_OUTPUT).unwrap();
+        assert_eq!(langs, vec!["rust".to_string()]);
+        assert!(is_stdout(&output));
+    }
+
+    #[test]
+    fn test_parse_args_stdout_flag_with_path() {
+        let mut args = pico_args::Arguments::from_vec(vec![
+            "--stdout".into(),
+            "rust".into(),
+            "out.gitignore".into(),
+        ]);
+        assert!(
+            parse_args(&mut args, DEFAULT_OUTPUT)
+                .unwrap_err()
+                .contains("--stdout")
+        );
+    }
+
+    #[test]
+    fn test_parse_detect_args_uses_output_directory() {
+        let dir = unique_dir("detect_args");
+        fs::write(dir.join("go.mod"), "module example\n").unwrap();
+        fs::write(dir.join("main.tf"), "").unwrap();
+        let output = dir.join(".gitignore");
+
+        let mut args = pico_args::Arguments::from_vec(vec![output.clone().into()]);
+        let (langs, parsed_output) = parse_detect_args(&mut args, DEFAULT_OUTPUT).unwrap();
+        assert_eq!(langs, vec!["go".to_string(), "terraform".to_string()]);
+        assert_eq!(parsed_output, output);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_detect_languages_nothing_found() {
+        let dir = unique_dir("detect_none");
+        let result = detect_languages(&dir);
+        assert!(result.unwrap_err().contains("no known project files"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn test_parse_args_missing_languages() {
         let mut args = pico_args::Arguments::from_vec(vec![]);
-        let result = parse_args(&mut args);
+        let result = parse_args(&mut args, DEFAULT_OUTPUT);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_args_empty_language_in_list() {
         let mut args = pico_args::Arguments::from_vec(vec!["go,,godot".into()]);
-        let result = parse_args(&mut args);
+        let result = parse_args(&mut args, DEFAULT_OUTPUT);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("empty language"));
     }
@@ -427,38 +2585,89 @@ mod tests {
 
     #[test]
     fn test_merge_templates_single() {
-        let templates = vec!["# Comment\n*.log\n"];
-        let result = merge_templates(&templates);
-        assert_eq!(result, "# Comment\n*.log\n");
+        let result = blocks::merge_blocks(None, &[("a", "# Comment\n*.log\n")]).unwrap();
+        assert_eq!(result, "# >>> gig: a\n# Comment\n*.log\n# <<< gig: a\n");
     }
 
     #[test]
     fn test_merge_templates_deduplicates_patterns() {
-        let templates = vec!["# First\n*.log\n", "# Second\n*.log\n*.txt\n"];
-        let result = merge_templates(&templates);
-        assert_eq!(result, "# First\n*.log\n# Second\n*.txt\n");
+        let templates = [("a", "# First\n*.log\n"), ("b", "# Second\n*.log\n*.txt\n")];
+        let result = blocks::merge_blocks(None, &templates).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: a\n# First\n*.log\n# <<< gig: a\n\n\
+             # >>> gig: b\n# Second\n*.txt\n# <<< gig: b\n"
+        );
     }
 
     #[test]
     fn test_merge_templates_preserves_comments() {
-        let templates = vec!["# Same comment\n*.a\n", "# Same comment\n*.b\n"];
-        let result = merge_templates(&templates);
-        assert_eq!(result, "# Same comment\n*.a\n# Same comment\n*.b\n");
+        let templates = [
+            ("a", "# Same comment\n*.a\n"),
+            ("b", "# Same comment\n*.b\n"),
+        ];
+        let result = blocks::merge_blocks(None, &templates).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: a\n# Same comment\n*.a\n# <<< gig: a\n\n\
+             # >>> gig: b\n# Same comment\n*.b\n# <<< gig: b\n"
+        );
     }
 
     #[test]
     fn test_merge_templates_preserves_blank_lines() {
-        let templates = vec!["*.a\n\n*.b\n", "*.c\n\n*.d\n"];
-        let result = merge_templates(&templates);
-        assert_eq!(result, "*.a\n\n*.b\n*.c\n\n*.d\n");
+        let templates = [("a", "*.a\n\n*.b\n"), ("b", "*.c\n\n*.d\n")];
+        let result = blocks::merge_blocks(None, &templates).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: a\n*.a\n\n*.b\n# <<< gig: a\n\n\
+             # >>> gig: b\n*.c\n\n*.d\n# <<< gig: b\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_templates_keeps_repeat_after_negation() {
+        // Dropping the second *.foo would let !keep.foo re-include keep.foo
+        let result = blocks::merge_blocks(None, &[("a", "*.foo\n!keep.foo\n"), ("b", "*.foo\n")]);
+        assert_eq!(
+            result.unwrap(),
+            "# >>> gig: a\n*.foo\n!keep.foo\n# <<< gig: a\n\n\
+             # >>> gig: b\n*.foo\n# <<< gig: b\n"
+        );
+
+        // A negation of something else doesn't block deduplication
+        let result = blocks::merge_blocks(None, &[("a", "*.foo\n!keep.bar\n"), ("b", "*.foo\n")]);
+        assert_eq!(
+            result.unwrap(),
+            "# >>> gig: a\n*.foo\n!keep.bar\n# <<< gig: a\n\n\
+             # >>> gig: b\n# <<< gig: b\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_templates_keeps_repeated_negation_after_reignore() {
+        let templates = [
+            ("a", ".idea/*\n!.idea/icon.svg\n"),
+            ("b", ".idea/*\n"),
+            ("c", "!.idea/icon.svg\n"),
+        ];
+        let result = blocks::merge_blocks(None, &templates).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: a\n.idea/*\n!.idea/icon.svg\n# <<< gig: a\n\n\
+             # >>> gig: b\n.idea/*\n# <<< gig: b\n\n\
+             # >>> gig: c\n!.idea/icon.svg\n# <<< gig: c\n"
+        );
     }
 
     #[test]
     fn test_merge_templates_exact_match_only() {
         // *.LOG and *.log are different patterns
-        let templates = vec!["*.log\n", "*.LOG\n"];
-        let result = merge_templates(&templates);
-        assert_eq!(result, "*.log\n*.LOG\n");
+        let result = blocks::merge_blocks(None, &[("a", "*.log\n"), ("b", "*.LOG\n")]).unwrap();
+        assert_eq!(
+            result,
+            "# >>> gig: a\n*.log\n# <<< gig: a\n\n# >>> gig: b\n*.LOG\n# <<< gig: b\n"
+        );
     }
 
     #[test]
@@ -478,7 +2687,7 @@ mod tests {
         let go = get_template("go").unwrap();
         let rust = get_template("rust").unwrap();
 
-        let merged = merge_templates(&[go, rust]);
+        let merged = blocks::merge_blocks(None, &[("go", go), ("rust", rust)]).unwrap();
 
         // Verify merged content contains patterns from both
         assert!(
@@ -534,6 +2743,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_template_unique_suffix() {
+        let (key, content) = resolve_template("hugo").unwrap();
+        assert_eq!(key, "community.golang.hugo");
+        assert_eq!(content, get_template("community.golang.hugo").unwrap());
+
+        let (key, _) = resolve_template("Vue").unwrap();
+        assert_eq!(key, "community.javascript.vue");
+    }
+
+    #[test]
+    fn test_resolve_template_ambiguous_suffix() {
+        let err = resolve_template("coldbox").unwrap_err();
+        assert!(err.contains("ambiguous language"), "got: {err}");
+        assert!(err.contains("community.boxlang.coldbox, community.cfml.coldbox"));
+    }
+
+    #[test]
+    fn test_resolve_template_top_level_priority() {
+        let (key, _) = resolve_template("al").unwrap();
+        assert_eq!(key, "al");
+    }
+
     #[test]
     fn test_community_template_access() {
         // Vue is in community/JavaScript/, should be accessible with full prefix
@@ -646,7 +2878,7 @@ mod tests {
         let existing = "# Existing\n*.log\n*.tmp\n";
         let new_template = "# New\n*.log\n*.o\n";
 
-        let merged = merge_templates(&[existing, new_template]);
+        let merged = blocks::merge_blocks(Some(existing), &[("new", new_template)]).unwrap();
 
         // *.log should appear only once (from existing)
         let log_count = merged.lines().filter(|l| l.trim() == "*.log").count();
@@ -676,7 +2908,7 @@ mod tests {
         // Simulate the full append flow: read existing, merge with Go template, write back
         let existing = read_existing_file(&path).unwrap().unwrap();
         let go = get_template("go").unwrap();
-        let merged = merge_templates(&[existing.as_str(), go]);
+        let merged = blocks::merge_blocks(Some(&existing), &[("go", go)]).unwrap();
         write_output(&path, &merged, true).unwrap();
 
         let result = fs::read_to_string(&path).unwrap();
@@ -692,6 +2924,197 @@ mod tests {
         fs::remove_dir_all(&dir).ok();
     }
 
+    #[test]
+    fn test_parse_output_mode() {
+        let mut args = pico_args::Arguments::from_vec(vec!["--diff".into(), "rust".into()]);
+        assert_eq!(parse_output_mode(&mut args), Ok(OutputMode::Diff));
+
+        let mut args = pico_args::Arguments::from_vec(vec!["--dry-run".into()]);
+        assert_eq!(parse_output_mode(&mut args), Ok(OutputMode::DryRun));
+
+        let mut args = pico_args::Arguments::from_vec(vec!["rust".into()]);
+        assert_eq!(parse_output_mode(&mut args), Ok(OutputMode::Write));
+    }
+
+    #[test]
+    fn test_parse_output_mode_conflict() {
+        let mut args = pico_args::Arguments::from_vec(vec!["--dry-run".into(), "--diff".into()]);
+        assert!(parse_output_mode(&mut args).is_err());
+    }
+
+    #[test]
+    fn test_emit_output_preview_leaves_file_untouched() {
+        let dir = unique_dir("preview_untouched");
+        let path = dir.join(".gitignore");
+        fs::write(&path, "old\n").unwrap();
+
+        emit_output(&path, "new\n", true, OutputMode::DryRun).unwrap();
+        emit_output(&path, "new\n", true, OutputMode::Diff).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\n");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_emit_output_preview_refuses_existing_without_append() {
+        let dir = unique_dir("preview_refuses");
+        let path = dir.join(".gitignore");
+        fs::write(&path, "old\n").unwrap();
+
+        let result = emit_output(&path, "new\n", false, OutputMode::DryRun);
+        assert!(result.unwrap_err().contains("already exists"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_free_paths_rejects_unknown_options() {
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            free_paths(args(&["build/", "a.log"]), Vec::new()),
+            Ok(vec!["build/".to_string(), "a.log".to_string()])
+        );
+        assert_eq!(
+            free_paths(args(&["build/", "--template=go"]), Vec::new()),
+            Err("unknown option '--template=go' (put paths starting with - after --)".to_string())
+        );
+        assert_eq!(
+            free_paths(args(&["-"]), Vec::new()),
+            Ok(vec!["-".to_string()])
+        );
+        assert_eq!(
+            free_paths(args(&["build/"]), args(&["-x", "--file"])),
+            Ok(vec![
+                "build/".to_string(),
+                "-x".to_string(),
+                "--file".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_positionals_limits_count() {
+        let mut args = pico_args::Arguments::from_vec(vec!["a".into(), "--".into(), "-b".into()]);
+        let after = split_at_dashes(&mut args);
+        assert_eq!(
+            positionals(&mut args, after, 2),
+            Ok(vec!["a".to_string(), "-b".to_string()])
+        );
+
+        let mut args = pico_args::Arguments::from_vec(vec!["a".into(), "b".into()]);
+        let error = positionals(&mut args, Vec::new(), 1).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+        assert_eq!(error.message, "unexpected argument 'b'");
+    }
+
+    #[test]
+    fn test_lint_rejects_unknown_option() {
+        let mut args = pico_args::Arguments::from_vec(vec!["--bogus".into()]);
+        let error = run_lint(&mut args, false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+        assert!(error.message.contains("unknown option '--bogus'"));
+    }
+
+    #[test]
+    fn test_fmt_rejects_misspelled_option_without_writing() {
+        let dir = unique_dir("fmt_option");
+        let path = dir.join(".gitignore");
+        fs::write(&path, "a\n\n\nb  \n").unwrap();
+
+        let mut args = pico_args::Arguments::from_vec(vec![path.clone().into(), "--chek".into()]);
+        let error = run_fmt(&mut args, false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\n\n\nb  \n");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_explain_rejects_extra_arguments() {
+        let args = |list: &[&str]| {
+            pico_args::Arguments::from_vec(list.iter().map(OsString::from).collect())
+        };
+        let error = run_explain(&mut args(&["a", "*.log", "--verbose"]), false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+        let error = run_explain(&mut args(&["a", "*.log", "b"]), false).unwrap_err();
+        assert_eq!(error.message, "unexpected argument 'b'");
+        let error = run_explain(&mut args(&["a"]), false).unwrap_err();
+        assert_eq!(error.message, "usage: gig explain <file> <pattern>");
+    }
+
+    #[test]
+    fn test_remove_rejects_unknown_option() {
+        let dir = unique_dir("remove_option");
+        let path = dir.join(".gitignore");
+        fs::write(&path, "*.log\n").unwrap();
+
+        let mut args = pico_args::Arguments::from_vec(vec![
+            "go".into(),
+            path.clone().into(),
+            "--dryrun".into(),
+        ]);
+        let error = run_remove(&mut args, false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+        assert!(error.message.contains("unknown option '--dryrun'"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "*.log\n");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_preview_rejects_unknown_option() {
+        let mut args =
+            pico_args::Arguments::from_vec(vec!["go".into(), ".".into(), "--tracke".into()]);
+        let error = run_preview(&mut args, false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+        assert!(error.message.contains("unknown option '--tracke'"));
+    }
+
+    #[test]
+    fn test_show_errors_are_coded() {
+        let args = |list: &[&str]| {
+            pico_args::Arguments::from_vec(list.iter().map(OsString::from).collect())
+        };
+        let error = run_show(&mut args(&["no-such-template"]), true).unwrap_err();
+        assert_eq!(error.code, ErrorCode::UnknownTemplate);
+        let error = run_show(&mut args(&[]), true).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+        let error = run_show(&mut args(&["python", "--pager"]), true).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Usage);
+    }
+
+    #[test]
+    fn test_generate_matches_check_ignore_source() {
+        let content = generate(&["go".to_string(), "c".to_string()], None).unwrap();
+        assert_eq!(blocks::block_names(&content).unwrap(), vec!["go", "c"]);
+
+        let matcher = matcher::Matcher::parse(&content);
+        let decided = matcher.decide("bin/app.exe", false).unwrap();
+        assert!(!decided.negated);
+        assert_eq!(blocks::line_blocks(&content)[decided.line - 1], Some("go"));
+    }
+
+    #[test]
+    fn test_regenerate_follows_record() {
+        let entry = lockfile::Entry {
+            path: ".gitignore".to_string(),
+            templates: vec!["go".to_string()],
+            revision: None,
+            header: false,
+            settings: Settings::default(),
+        };
+        let existing = "custom/\n\n# >>> gig: go\nstale\n# <<< gig: go\n\n# >>> gig: node\nnode_modules/\n# <<< gig: node\n";
+
+        let content = regenerate(&entry, Some(existing)).unwrap();
+        assert!(content.starts_with("custom/\n"));
+        assert_eq!(blocks::block_names(&content).unwrap(), vec!["go"]);
+        assert!(!content.contains("stale"));
+        assert_eq!(regenerate(&entry, Some(&content)).unwrap(), content);
+
+        let fresh = regenerate(&entry, None).unwrap();
+        assert!(fresh.starts_with("# >>> gig: go\n"));
+    }
+
     #[test]
     fn test_read_existing_file_error_on_unreadable() {
         let dir = unique_dir("read_unreadable");
@@ -0,0 +1,574 @@
+//! Gitignore pattern semantics: which paths a set of patterns ignores, and
+//! which line decides it.
+//!
+//! Follows gitignore(5): `#` comments, `\` escapes, trailing-space trimming,
+//! `!` negation, trailing `/` for directories only, anchoring by a leading or
+//! inner `/`, `*`, `?`, bracket classes and the `**` forms. As in git, a path
+//! inside an ignored directory stays ignored even if a later `!` pattern
+//! matches it.
+
+/// One element of a compiled glob.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `?`: any character except `/`.
+    AnyChar,
+    /// `*`: any run of characters except `/`.
+    Star,
+    /// `**/` at the start or `/**/` in the middle: zero or more directories.
+    AnyDirs,
+    /// `/**` at the end: everything inside.
+    AnyRest,
+    /// An unclosed `[` or a trailing `\`: git gives up on the pattern, so it
+    /// never matches.
+    Invalid,
+    Class {
+        negated: bool,
+        items: Vec<ClassItem>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Named(String),
+}
+
+/// A parsed line of a gitignore file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    /// 1-based line number in the source.
+    pub line: usize,
+    /// The line as written, without trailing whitespace.
+    pub text: String,
+    /// Starts with `!`: re-includes matching paths.
+    pub negated: bool,
+    /// Ends with `/`: matches directories only.
+    pub dir_only: bool,
+    /// Contains a `/` before the end: matched from the root, not at any depth.
+    anchored: bool,
+    tokens: Vec<Token>,
+}
+
+impl Pattern {
+    /// Parse a gitignore line, or None for blanks and comments.
+    pub fn parse(line: &str, lineno: usize) -> Option<Pattern> {
+        let text = trim_trailing_spaces(line);
+        if text.is_empty() || text.starts_with('#') {
+            return None;
+        }
+
+        let (negated, mut body) = match text.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let dir_only = body.ends_with('/') && !body.ends_with("\\/");
+        if dir_only {
+            body = &body[..body.len() - 1];
+        }
+        if body.is_empty() {
+            return None;
+        }
+
+        let anchored = body.contains('/');
+        let body = body.strip_prefix('/').unwrap_or(body);
+        let tokens = compile(body);
+
+        Some(Pattern {
+            line: lineno,
+            text: text.to_string(),
+            negated,
+            dir_only,
+            anchored,
+            tokens,
+        })
+    }
+
+    /// Whether the pattern is matched from the root rather than at any depth.
+    pub fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    /// Whether this pattern matches `path` (relative, `/`-separated), ignoring
+    /// negation.
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let path: Vec<char> = path.chars().collect();
+        if self.anchored {
+            glob_match(&self.tokens, &path)
+        } else {
+            let name_start = path.iter().rposition(|&c| c == '/').map_or(0, |i| i + 1);
+            glob_match(&self.tokens, &path[name_start..])
+        }
+    }
+}
+
+/// Whether two pattern lines could match the same path, ignoring negation.
+///
+/// Errs towards yes, since deduplication relies on it to keep a repeat that a
+/// negation in between makes meaningful. It says no only when that is
+/// certain: one pattern is a literal path or name the other can't match, or
+/// the last segments start or end with literal text no one name has both of.
+/// So `*.log` doesn't overlap `keep.txt` or `*.txt`, but `foo*` overlaps `*bar`.
+pub fn overlaps(a: &str, b: &str) -> bool {
+    let (Some(pa), Some(pb)) = (Pattern::parse(a, 0), Pattern::parse(b, 0)) else {
+        return false;
+    };
+    let (body_a, body_b) = (body(&pa), body(&pb));
+    if is_literal(body_a) {
+        literal_overlaps(&pa, body_a, &pb, body_b)
+    } else if is_literal(body_b) {
+        literal_overlaps(&pb, body_b, &pa, body_a)
+    } else {
+        !disjoint_names(last_segment(body_a), last_segment(body_b))
+    }
+}
+
+/// Whether two pattern lines certainly match a path in common, ignoring
+/// negation: one of their texts, taken as a path, is matched by both. Where
+/// `overlaps` says yes but this says no, they only might.
+pub fn shares_path(a: &str, b: &str) -> bool {
+    let (Some(pa), Some(pb)) = (Pattern::parse(a, 0), Pattern::parse(b, 0)) else {
+        return false;
+    };
+    [body(&pa), body(&pb)].into_iter().any(|path| {
+        [false, true]
+            .into_iter()
+            .any(|is_dir| pa.matches(path, is_dir) && pb.matches(path, is_dir))
+    })
+}
+
+/// A pattern's text without `!`, a leading `/` and a trailing `/`.
+fn body(p: &Pattern) -> &str {
+    let body = p.text.strip_prefix('!').unwrap_or(&p.text);
+    let body = if p.dir_only {
+        &body[..body.len() - 1]
+    } else {
+        body
+    };
+    body.strip_prefix('/').unwrap_or(body)
+}
+
+fn is_literal(body: &str) -> bool {
+    !body.contains(GLOB_CHARS)
+}
+
+fn last_segment(body: &str) -> &str {
+    body.rsplit('/').next().unwrap_or(body)
+}
+
+/// Whether the literal pattern `literal` and `other` match a path in common.
+fn literal_overlaps(
+    literal: &Pattern,
+    literal_body: &str,
+    other: &Pattern,
+    other_body: &str,
+) -> bool {
+    let matches = |p: &Pattern, path: &str| p.matches(path, false) || p.matches(path, true);
+    if literal.anchored {
+        return matches(other, literal_body);
+    }
+    // A name at any depth: the other pattern's last segment decides the name
+    if !other.anchored {
+        return matches(other, literal_body);
+    }
+    Pattern::parse(last_segment(other_body), 0).is_some_and(|last| matches(&last, literal_body))
+}
+
+/// Whether no name can match both glob segments, judged by the literal text
+/// before their first and after their last wildcard.
+fn disjoint_names(a: &str, b: &str) -> bool {
+    let (pa, pb) = (literal_prefix(a), literal_prefix(b));
+    let (sa, sb) = (literal_suffix(a), literal_suffix(b));
+    !(pa.starts_with(pb) || pb.starts_with(pa)) || !(sa.ends_with(sb) || sb.ends_with(sa))
+}
+
+/// Characters that end a run of literal text in a glob. `]` is included so a
+/// class's contents never count as literal.
+const GLOB_CHARS: &[char] = &['*', '?', '[', ']', '\\'];
+
+fn literal_prefix(glob: &str) -> &str {
+    &glob[..glob.find(GLOB_CHARS).unwrap_or(glob.len())]
+}
+
+fn literal_suffix(glob: &str) -> &str {
+    &glob[glob.rfind(GLOB_CHARS).map_or(0, |i| i + 1)..]
+}
+
+/// The patterns of one gitignore file, in order.
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    pub patterns: Vec<Pattern>,
+}
+
+impl Matcher {
+    pub fn parse(content: &str) -> Matcher {
+        let patterns = content
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| Pattern::parse(line, i + 1))
+            .collect();
+        Matcher { patterns }
+    }
+
+    /// The pattern that decides whether `path` is ignored, if any matches.
+    ///
+    /// The path is ignored when the deciding pattern isn't negated. Leading
+    /// directories are checked first, since git doesn't look inside an
+    /// ignored directory.
+    pub fn decide(&self, path: &str, is_dir: bool) -> Option<&Pattern> {
+        let written = path.trim_start_matches("./");
+        let path = written.trim_end_matches('/');
+        for (i, _) in path.match_indices('/') {
+            if let Some(pattern) = self.last_match(&path[..i], true)
+                && !pattern.negated
+            {
+                return Some(pattern);
+            }
+        }
+        if written == path {
+            return self.last_match(path, is_dir);
+        }
+        // As in `git check-ignore`, a directory given with its trailing `/`
+        // is also matched as written, so `bar/**` applies to `bar/`
+        self.patterns
+            .iter()
+            .rev()
+            .find(|p| p.matches(path, is_dir) || (p.anchored && p.matches(written, is_dir)))
+    }
+
+    /// The last pattern matching `path` itself, without looking at its
+    /// leading directories.
+    pub fn last_match(&self, path: &str, is_dir: bool) -> Option<&Pattern> {
+        self.patterns.iter().rev().find(|p| p.matches(path, is_dir))
+    }
+}
+
+/// Drop trailing spaces unless escaped with a backslash.
+pub fn trim_trailing_spaces(line: &str) -> &str {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let mut end = line.len();
+    while line[..end].ends_with(' ') {
+        let before = &line[..end - 1];
+        let backslashes = before.len() - before.trim_end_matches('\\').len();
+        if backslashes % 2 == 1 {
+            break;
+        }
+        end -= 1;
+    }
+    &line[..end]
+}
+
+fn compile(glob: &str) -> Vec<Token> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                tokens.push(Token::Literal(chars[i + 1]));
+                i += 2;
+            }
+            '\\' => {
+                tokens.push(Token::Invalid);
+                i += 1;
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                let mut end = i;
+                while chars.get(end) == Some(&'*') {
+                    end += 1;
+                }
+                match chars.get(end) {
+                    Some('/') if at_start => {
+                        tokens.push(Token::AnyDirs);
+                        end += 1;
+                    }
+                    None if at_start => tokens.push(Token::AnyRest),
+                    // Any other run of asterisks is a plain `*`
+                    _ => tokens.push(Token::Star),
+                }
+                i = end;
+            }
+            '*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            '?' => {
+                tokens.push(Token::AnyChar);
+                i += 1;
+            }
+            '[' => match compile_class(&chars, i) {
+                Some((token, end)) => {
+                    tokens.push(token);
+                    i = end;
+                }
+                None => {
+                    tokens.push(Token::Invalid);
+                    i += 1;
+                }
+            },
+            c => {
+                tokens.push(Token::Literal(c));
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Compile the bracket expression starting at `chars[start]`, returning it and
+/// the index just past its `]`, or None if it is never closed.
+fn compile_class(chars: &[char], start: usize) -> Option<(Token, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(chars.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut items = Vec::new();
+    let mut first = true;
+    loop {
+        let c = *chars.get(i)?;
+        if c == ']' && !first {
+            return Some((Token::Class { negated, items }, i + 1));
+        }
+        first = false;
+
+        if c == '[' && chars.get(i + 1) == Some(&':') {
+            let rest: String = chars[i + 2..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                let name: String = rest[..end].to_string();
+                i += 2 + name.chars().count() + 2;
+                items.push(ClassItem::Named(name));
+                continue;
+            }
+        }
+
+        let (lo, next) = match c {
+            '\\' => (*chars.get(i + 1)?, i + 2),
+            c => (c, i + 1),
+        };
+        if chars.get(next) == Some(&'-') && chars.get(next + 1).is_some_and(|&c| c != ']') {
+            let (hi, after) = match chars[next + 1] {
+                '\\' => (*chars.get(next + 2)?, next + 3),
+                c => (c, next + 2),
+            };
+            items.push(ClassItem::Range(lo, hi));
+            i = after;
+        } else {
+            items.push(ClassItem::Char(lo));
+            i = next;
+        }
+    }
+}
+
+fn class_matches(items: &[ClassItem], c: char) -> bool {
+    items.iter().any(|item| match item {
+        ClassItem::Char(x) => *x == c,
+        ClassItem::Range(lo, hi) => (*lo..=*hi).contains(&c),
+        ClassItem::Named(name) => match name.as_str() {
+            "alnum" => c.is_ascii_alphanumeric(),
+            "alpha" => c.is_ascii_alphabetic(),
+            "blank" => c == ' ' || c == '\t',
+            "cntrl" => c.is_ascii_control(),
+            "digit" => c.is_ascii_digit(),
+            "graph" => c.is_ascii_graphic(),
+            "lower" => c.is_ascii_lowercase(),
+            "print" => c.is_ascii_graphic() || c == ' ',
+            "punct" => c.is_ascii_punctuation(),
+            "space" => c.is_ascii_whitespace(),
+            "upper" => c.is_ascii_uppercase(),
+            "xdigit" => c.is_ascii_hexdigit(),
+            _ => false,
+        },
+    })
+}
+
+fn glob_match(tokens: &[Token], text: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+
+    match token {
+        Token::Literal(c) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+        Token::AnyChar => text.first().is_some_and(|&c| c != '/') && glob_match(rest, &text[1..]),
+        Token::Class { negated, items } => text.first().is_some_and(|&c| {
+            c != '/' && class_matches(items, c) != *negated && glob_match(rest, &text[1..])
+        }),
+        Token::Star => {
+            let segment = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=segment).any(|n| glob_match(rest, &text[n..]))
+        }
+        Token::AnyDirs => {
+            // Zero directories, or skip to just after any later `/`
+            glob_match(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &c)| c == '/')
+                    .any(|(i, _)| glob_match(rest, &text[i + 1..]))
+        }
+        Token::AnyRest => true,
+        Token::Invalid => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(patterns: &str, path: &str) -> bool {
+        Matcher::parse(patterns)
+            .decide(path, path.ends_with('/'))
+            .is_some_and(|p| !p.negated)
+    }
+
+    #[test]
+    fn test_parse_skips_comments_and_blanks() {
+        let matcher = Matcher::parse("# comment\n\n*.log\n  \n");
+        assert_eq!(matcher.patterns.len(), 1);
+        assert_eq!(matcher.patterns[0].line, 3);
+    }
+
+    #[test]
+    fn test_unanchored_matches_at_any_depth() {
+        assert!(ignored("*.log", "debug.log"));
+        assert!(ignored("*.log", "logs/debug.log"));
+        assert!(ignored("build", "src/build"));
+        assert!(!ignored("*.log", "debug.log.txt"));
+    }
+
+    #[test]
+    fn test_anchored_patterns() {
+        assert!(ignored("/build", "build"));
+        assert!(!ignored("/build", "src/build"));
+        assert!(ignored("doc/*.txt", "doc/notes.txt"));
+        assert!(!ignored("doc/*.txt", "doc/server/arch.txt"));
+        assert!(!ignored("doc/*.txt", "other/doc/notes.txt"));
+    }
+
+    #[test]
+    fn test_directory_only_patterns() {
+        assert!(ignored("build/", "build/"));
+        assert!(!ignored("build/", "build"));
+        // Files inside a matched directory are ignored with it
+        assert!(ignored("build/", "build/out.o"));
+        assert!(ignored("build/", "src/build/out.o"));
+    }
+
+    #[test]
+    fn test_double_star_forms() {
+        assert!(ignored("**/foo", "foo"));
+        assert!(ignored("**/foo", "a/b/foo"));
+        assert!(ignored("**/foo/bar", "x/foo/bar"));
+        assert!(ignored("abc/**", "abc/x/y"));
+        assert!(!ignored("abc/**", "abc"));
+        assert!(ignored("a/**/b", "a/b"));
+        assert!(ignored("a/**/b", "a/x/y/b"));
+        assert!(!ignored("a/**/b", "xa/b"));
+        // As written with its `/`, the directory itself matches, but not
+        // what is directly inside it
+        assert!(ignored("bar/**/", "bar/"));
+        assert!(ignored("bar/**/", "bar/x/"));
+        assert!(ignored("bar/**/", "bar/x/g"));
+        assert!(!ignored("bar/**/", "bar/f"));
+        // Not a whole component: behaves like a single star
+        assert!(ignored("foo**bar", "fooXbar"));
+        assert!(!ignored("/foo**bar", "foo/bar"));
+    }
+
+    #[test]
+    fn test_negation_last_match_wins() {
+        let patterns = "*.log\n!keep.log\n";
+        assert!(ignored(patterns, "debug.log"));
+        assert!(!ignored(patterns, "keep.log"));
+
+        let matcher = Matcher::parse(patterns);
+        let decided = matcher.decide("keep.log", false).unwrap();
+        assert!(decided.negated);
+        assert_eq!(decided.line, 2);
+    }
+
+    #[test]
+    fn test_negation_cannot_reinclude_inside_ignored_dir() {
+        let patterns = "build/\n!build/keep.txt\n";
+        assert!(ignored(patterns, "build/keep.txt"));
+        let matcher = Matcher::parse(patterns);
+        assert_eq!(matcher.decide("build/keep.txt", false).unwrap().line, 1);
+
+        // Ignoring the contents rather than the directory allows it
+        let patterns = "build/*\n!build/keep.txt\n";
+        assert!(!ignored(patterns, "build/keep.txt"));
+        assert!(ignored(patterns, "build/other.txt"));
+    }
+
+    #[test]
+    fn test_escapes_and_trailing_spaces() {
+        assert!(ignored("\\#notes", "#notes"));
+        assert!(ignored("\\!important", "!important"));
+        assert!(ignored("\\*", "*"));
+        assert!(!ignored("\\*", "x"));
+        assert!(ignored("foo   ", "foo"));
+        assert!(ignored("foo\\ ", "foo "));
+        assert!(!ignored("foo\\ ", "foo"));
+        // A trailing backslash never matches, as in git
+        assert!(!ignored("foo\\", "foo\\"));
+        assert!(!ignored("foo\\", "foo"));
+    }
+
+    #[test]
+    fn test_character_classes() {
+        assert!(ignored("*.py[cod]", "a.pyc"));
+        assert!(!ignored("*.py[cod]", "a.pyx"));
+        assert!(ignored("file[0-9].txt", "file7.txt"));
+        assert!(!ignored("file[!0-9].txt", "file7.txt"));
+        assert!(ignored("file[^0-9].txt", "fileA.txt"));
+        assert!(ignored("[]]", "]"));
+        assert!(ignored("x[[:digit:]]", "x4"));
+        assert!(!ignored("a[/]b", "a/b"));
+        // An unclosed bracket never matches, as in git
+        assert!(!ignored("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        assert!(overlaps("*.log", "!keep.log"));
+        assert!(overlaps("!*.meta", "*.meta"));
+        assert!(overlaps(".vscode/*", "!.vscode/settings.json"));
+        assert!(!overlaps("*.log", "!keep.txt"));
+        assert!(!overlaps("build/", "!dist/"));
+        assert!(!overlaps("!*.log", "*.txt"));
+        assert!(!overlaps("/src/*.o", "!main.c"));
+        assert!(overlaps("/src/*.o", "!main.o"));
+        assert!(overlaps("src/**", "!keep"));
+    }
+
+    #[test]
+    fn test_shares_path() {
+        assert!(shares_path("*.log", "!keep.log"));
+        assert!(shares_path("*.log", "!*.log"));
+        assert!(shares_path("build/", "!build"));
+        assert!(!shares_path("foo*", "!*bar"));
+        assert!(!shares_path("*.log", "!keep.txt"));
+    }
+
+    #[test]
+    fn test_overlaps_wildcards_unless_provably_disjoint() {
+        assert!(overlaps("foo*", "!*bar"));
+        assert!(overlaps("*.py[cod]", "!*.pyc"));
+        assert!(overlaps("a?c", "!abc*"));
+        assert!(overlaps("**", "!*.txt"));
+    }
+
+    #[test]
+    fn test_question_mark_stays_in_component() {
+        assert!(ignored("a?c", "abc"));
+        assert!(!ignored("/a?c", "a/c"));
+    }
+}
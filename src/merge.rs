@@ -0,0 +1,216 @@
+//! Negation-aware deduplication of pattern lines, shared by merging
+//! templates, `gig lint` and `gig fmt`.
+
+use crate::{matcher, normalize};
+
+/// How patterns repeated across templates are recognised.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Dedupe {
+    /// Only identical lines.
+    #[default]
+    Exact,
+    /// Lines an earlier one covers, per `normalize::covers`.
+    Semantic,
+}
+
+/// A template line left out because an earlier line already covers it.
+#[derive(Debug, PartialEq)]
+pub struct Dropped {
+    pub pattern: String,
+    /// The earlier line that covers it.
+    pub covered_by: String,
+}
+
+/// Patterns already written, in order, for negation-aware deduplication.
+#[derive(Debug, Default)]
+pub struct Seen<'a> {
+    dedupe: Dedupe,
+    pub order: Vec<&'a str>,
+    /// Where each pattern in `order` came from, by position.
+    pub provenance: Vec<Provenance<'a>>,
+    /// Lines dropped by semantic deduplication that weren't exact repeats.
+    pub dropped: Vec<Dropped>,
+}
+
+/// Where a kept pattern was written and which templates repeated it.
+#[derive(Debug, Default)]
+pub struct Provenance<'a> {
+    /// Byte offset of its line in the merged output, if this merge wrote it.
+    pub offset: Option<usize>,
+    /// Later templates whose copy was dropped, with the line as they had it.
+    pub also: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Seen<'a> {
+    pub fn new(dedupe: Dedupe) -> Seen<'a> {
+        Seen {
+            dedupe,
+            ..Seen::default()
+        }
+    }
+
+    /// Record a pattern that has been written.
+    pub fn insert(&mut self, pattern: &'a str) {
+        self.order.push(pattern);
+        self.provenance.push(Provenance::default());
+    }
+
+    /// Record a pattern this merge is writing at `offset` in the output.
+    pub fn insert_at(&mut self, pattern: &'a str, offset: usize) {
+        self.insert(pattern);
+        if let Some(last) = self.provenance.last_mut() {
+            last.offset = Some(offset);
+        }
+    }
+
+    pub fn extend(&mut self, patterns: impl IntoIterator<Item = &'a str>) {
+        for pattern in patterns {
+            self.insert(pattern);
+        }
+    }
+
+    /// The position in `order` of the earlier line that makes writing
+    /// `pattern` redundant, if any.
+    ///
+    /// That is the closest line of the same polarity (`!` or not) that covers
+    /// it, provided nothing of the opposite polarity since then could match
+    /// the same paths: git lets the last matching line win, so a repeat after
+    /// such a line still matters.
+    pub fn redundant_with(&self, pattern: &str) -> Option<usize> {
+        match self.covering(pattern) {
+            Some((i, between)) if between.is_empty() => Some(i),
+            _ => None,
+        }
+    }
+
+    /// The position in `order` of the closest earlier line of the same
+    /// polarity that covers `pattern`, with the positions of the lines of
+    /// opposite polarity since then that could match some of the same paths.
+    pub fn covering(&self, pattern: &str) -> Option<(usize, Vec<usize>)> {
+        let negated = pattern.starts_with('!');
+        let mut between = Vec::new();
+        for (i, &earlier) in self.order.iter().enumerate().rev() {
+            if earlier.starts_with('!') != negated {
+                if matcher::overlaps(pattern, earlier) {
+                    between.push(i);
+                }
+                continue;
+            }
+            let covered = match self.dedupe {
+                Dedupe::Exact => earlier == pattern,
+                Dedupe::Semantic => normalize::covers(earlier, pattern),
+            };
+            if covered {
+                return Some((i, between));
+            }
+        }
+        None
+    }
+}
+
+/// What a line of an ignore file is, as git reads it.
+#[derive(Debug, PartialEq)]
+pub enum LineKind<'a> {
+    Blank,
+    /// A `#` in the first column; an indented one starts a pattern.
+    Comment,
+    /// A pattern, with the trailing spaces git drops trimmed.
+    Pattern(&'a str),
+}
+
+impl<'a> LineKind<'a> {
+    pub fn of(line: &'a str) -> LineKind<'a> {
+        if line.trim().is_empty() {
+            LineKind::Blank
+        } else if line.starts_with('#') {
+            LineKind::Comment
+        } else {
+            LineKind::Pattern(matcher::trim_trailing_spaces(line))
+        }
+    }
+}
+
+/// Append a template's lines to `output`, skipping patterns whose repeat
+/// would be redundant (see `Seen::redundant_with`). Skipped lines are
+/// credited to `name` in the provenance of the line that covers them.
+pub fn push_deduped<'a>(
+    output: &mut String,
+    name: &'a str,
+    template: &'a str,
+    seen: &mut Seen<'a>,
+) {
+    for line in template.lines() {
+        // Comments and blank lines are always included
+        let LineKind::Pattern(trimmed) = LineKind::of(line) else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+
+        match seen.redundant_with(trimmed) {
+            Some(i) => {
+                seen.provenance[i].also.push((name, trimmed));
+                let earlier = seen.order[i];
+                if earlier != trimmed {
+                    seen.dropped.push(Dropped {
+                        pattern: trimmed.to_string(),
+                        covered_by: earlier.to_string(),
+                    });
+                }
+            }
+            None => {
+                seen.insert_at(trimmed, output.len());
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_kind() {
+        assert_eq!(LineKind::of("   "), LineKind::Blank);
+        assert_eq!(LineKind::of("# Logs"), LineKind::Comment);
+        assert_eq!(LineKind::of("*.log  "), LineKind::Pattern("*.log"));
+        assert_eq!(LineKind::of("*.log\\ \r"), LineKind::Pattern("*.log\\ "));
+        // Git reads these as patterns
+        assert_eq!(LineKind::of("  # x"), LineKind::Pattern("  # x"));
+        assert_eq!(LineKind::of(" build"), LineKind::Pattern(" build"));
+    }
+
+    #[test]
+    fn test_redundant_with_unless_negation_between() {
+        let mut seen = Seen::new(Dedupe::Exact);
+        seen.extend(["*.log", "build/"]);
+        assert_eq!(seen.redundant_with("*.log"), Some(0));
+        assert_eq!(seen.redundant_with("dist/"), None);
+
+        seen.insert("!keep.log");
+        assert_eq!(seen.redundant_with("*.log"), None);
+        assert_eq!(seen.redundant_with("build/"), Some(1));
+    }
+
+    #[test]
+    fn test_push_deduped_credits_repeats() {
+        let mut seen = Seen::new(Dedupe::Semantic);
+        let mut output = String::new();
+        push_deduped(&mut output, "a", "# A\nbuild/\n", &mut seen);
+        push_deduped(&mut output, "b", "build/\nbuild/**\nout/\n", &mut seen);
+        assert_eq!(output, "# A\nbuild/\nout/\n");
+        assert_eq!(
+            seen.provenance[0].also,
+            vec![("b", "build/"), ("b", "build/**")]
+        );
+        assert_eq!(
+            seen.dropped,
+            vec![Dropped {
+                pattern: "build/**".to_string(),
+                covered_by: "build/".to_string(),
+            }]
+        );
+    }
+}
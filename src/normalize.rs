@@ -0,0 +1,211 @@
+//! Normal forms of gitignore patterns, for `--dedupe=semantic`.
+//!
+//! Patterns that git treats the same are reduced to one form: trailing
+//! spaces trimmed, needless escapes dropped, `**/` and `/` prefixes folded
+//! into the anchoring, and a trailing `/**` recorded as "everything inside".
+
+use crate::matcher::{self, Pattern};
+
+/// A pattern line reduced to what git sees in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normal {
+    pub negated: bool,
+    /// Ends with `/`: matches directories only.
+    pub dir_only: bool,
+    /// Matched from the root rather than at any depth.
+    pub anchored: bool,
+    /// Ends with `/**`: matches everything inside `body`, not `body` itself.
+    pub contents: bool,
+    /// The glob, with only meaningful escapes kept.
+    pub body: String,
+}
+
+impl Normal {
+    /// Whether the body has no wildcards, so it names a single path.
+    fn is_literal(&self) -> bool {
+        let mut chars = self.body.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '*' | '?' | '[' => return false,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// The path a literal body names, with escapes removed.
+    fn literal_path(&self) -> String {
+        let mut path = String::new();
+        let mut chars = self.body.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => path.extend(chars.next()),
+                c => path.push(c),
+            }
+        }
+        path
+    }
+}
+
+/// Normalize a pattern line, or None for blanks and comments.
+pub fn normalize(line: &str) -> Option<Normal> {
+    let text = matcher::trim_trailing_spaces(line);
+    if text.is_empty() || text.starts_with('#') {
+        return None;
+    }
+
+    let (negated, mut body) = match text.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let dir_only = body.ends_with('/') && !body.ends_with("\\/");
+    if dir_only {
+        body = &body[..body.len() - 1];
+    }
+    let mut anchored = body.contains('/');
+    body = body.strip_prefix('/').unwrap_or(body);
+
+    // `**/x` matches x at any depth, just like an unanchored pattern
+    while let Some(rest) = body.strip_prefix("**/") {
+        body = rest;
+        anchored = false;
+    }
+    let contents = body.len() > 3 && body.ends_with("/**");
+    if contents {
+        body = &body[..body.len() - 3];
+    }
+    if body.is_empty() {
+        return None;
+    }
+
+    Some(Normal {
+        negated,
+        dir_only,
+        anchored,
+        contents,
+        body: unescape(body),
+    })
+}
+
+/// Drop escapes that don't change the meaning of a glob.
+fn unescape(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('*' | '?' | '[' | '\\'))) => {
+                out.push('\\');
+                out.push(next);
+                chars.next();
+            }
+            ('\\', Some(next)) => {
+                out.push(next);
+                chars.next();
+            }
+            (c, _) => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether `earlier` already ignores (or re-includes) everything `later`
+/// does, so `later` adds nothing after it. Both must have the same polarity.
+pub fn covers(earlier: &str, later: &str) -> bool {
+    let (Some(a), Some(b)) = (normalize(earlier), normalize(later)) else {
+        return false;
+    };
+    if a.negated != b.negated {
+        return false;
+    }
+
+    let same_glob = a.body == b.body && a.contents == b.contents;
+    if same_glob && (a.anchored == b.anchored || !a.anchored) && (!a.dir_only || b.dir_only) {
+        return true;
+    }
+
+    // Otherwise only a later pattern naming one path can be shown covered
+    if !b.is_literal() || (!b.anchored && b.body.contains('/')) {
+        return false;
+    }
+    let path = b.literal_path();
+    let Some(a) = Pattern::parse(earlier.strip_prefix('!').unwrap_or(earlier), 0) else {
+        return false;
+    };
+    if !b.anchored && a.is_anchored() {
+        return false;
+    }
+
+    // Everything inside an ignored directory is ignored with it
+    if !b.negated
+        && path
+            .match_indices('/')
+            .any(|(i, _)| a.matches(&path[..i], true))
+    {
+        return true;
+    }
+    if b.contents || b.dir_only {
+        a.matches(&path, true)
+    } else {
+        a.matches(&path, true) && a.matches(&path, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_folds_equivalent_forms() {
+        let plain = normalize("build/").unwrap();
+        assert_eq!(normalize("**/build/"), Some(plain.clone()));
+        assert_eq!(normalize("/**/build/"), Some(plain.clone()));
+        assert_eq!(normalize("build/   "), Some(plain));
+
+        assert_eq!(normalize("/a/b"), normalize("a/b"));
+        assert_eq!(normalize("\\b\\uild"), normalize("build"));
+        assert_ne!(normalize("\\*.log"), normalize("*.log"));
+        assert_eq!(normalize("# comment"), None);
+    }
+
+    #[test]
+    fn test_normalize_contents() {
+        let n = normalize("build/**").unwrap();
+        assert!(n.contents && n.anchored);
+        assert_eq!(n.body, "build");
+    }
+
+    #[test]
+    fn test_covers_build_variants() {
+        for later in ["/build", "build/", "build/**", "**/build/", "/build/"] {
+            assert!(covers("build", later), "build should cover {later}");
+        }
+        assert!(covers("build/", "build/**"));
+        assert!(covers("build/", "/build/"));
+        // A directory pattern doesn't cover a file of the same name
+        assert!(!covers("build/", "/build"));
+        // Anchored doesn't cover unanchored
+        assert!(!covers("/build", "build"));
+        // '#' only starts a comment at the beginning of a line
+        assert!(!covers("build", "build # comment"));
+    }
+
+    #[test]
+    fn test_covers_wildcards() {
+        assert!(covers("*.log", "debug.log"));
+        assert!(covers("*.log", "/logs/debug.log"));
+        assert!(!covers("debug.log", "*.log"));
+        assert!(!covers("logs/*.log", "debug.log"));
+        assert!(covers("*.py[cod]", "*.py[cod]"));
+        assert!(covers("build/", "/build/out/app.o"));
+        assert!(!covers("!build/", "!build/keep.txt"));
+    }
+
+    #[test]
+    fn test_covers_needs_same_polarity() {
+        assert!(!covers("*.log", "!keep.log"));
+        assert!(covers("!keep.log", "!/keep.log"));
+    }
+}
@@ -0,0 +1,305 @@
+//! What adding templates to a directory's .gitignore would newly ignore.
+//!
+//! The tree is walked the way git walks it: every .gitignore applies to its
+//! own directory and below, deeper files take precedence, and ignored
+//! directories are not descended into.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::matcher::{Matcher, Pattern};
+
+/// A path that the new rules ignore and the current rules don't.
+#[derive(Debug, PartialEq)]
+pub struct Hit {
+    /// Relative to the root, with a trailing `/` for directories.
+    pub path: String,
+    /// The deciding line in the new root .gitignore.
+    pub line: usize,
+    pub pattern: String,
+    /// For a directory, the number of files under it.
+    pub files: usize,
+}
+
+/// The outcome of a preview.
+#[derive(Debug, Default, PartialEq)]
+pub struct Preview {
+    /// Untracked files and directories that would become ignored.
+    pub ignored: Vec<Hit>,
+    /// Tracked files the new patterns match; git keeps tracking them.
+    pub tracked: Vec<Hit>,
+}
+
+/// The patterns of one .gitignore, applying to paths under `prefix`.
+struct Layer {
+    /// `""` for the root, else the directory path with a trailing `/`.
+    prefix: String,
+    matcher: Matcher,
+}
+
+/// Compare the rules in `root` with the root .gitignore replaced by
+/// `new_root`. Files in `tracked` (relative to `root`) are reported separately.
+pub fn preview(root: &Path, new_root: &str, tracked: &HashSet<String>) -> Result<Preview, String> {
+    let path = root.join(".gitignore");
+    let current_root = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+    };
+
+    let mut walk = Walk {
+        current: vec![root_layer(&current_root)],
+        new: vec![root_layer(new_root)],
+        tracked,
+        preview: Preview::default(),
+    };
+    walk.dir(root, "")?;
+
+    // Tracked files are matched by path, whether or not the walk reached them
+    let mut tracked: Vec<&String> = tracked.iter().collect();
+    tracked.sort_unstable();
+    for path in tracked {
+        if is_ignored(&walk.current, path, false).is_some() {
+            continue;
+        }
+        if let Some(pattern) = is_ignored(&walk.new, path, false) {
+            walk.preview.tracked.push(Hit {
+                path: path.clone(),
+                line: pattern.line,
+                pattern: pattern.text.clone(),
+                files: 0,
+            });
+        }
+    }
+
+    Ok(walk.preview)
+}
+
+/// Tracked files (relative to the .gitignore's directory) that `new` ignores
+/// and `old` doesn't, considering that one file only.
+pub fn tracked_newly_ignored(old: &str, new: &str, tracked: &[String]) -> Vec<Hit> {
+    let old = [root_layer(old)];
+    let new = [root_layer(new)];
+    tracked
+        .iter()
+        .filter(|path| is_ignored(&old, path, false).is_none())
+        .filter_map(|path| {
+            let pattern = is_ignored(&new, path, false)?;
+            Some(Hit {
+                path: path.clone(),
+                line: pattern.line,
+                pattern: pattern.text.clone(),
+                files: 0,
+            })
+        })
+        .collect()
+}
+
+struct Walk<'a> {
+    current: Vec<Layer>,
+    new: Vec<Layer>,
+    tracked: &'a HashSet<String>,
+    preview: Preview,
+}
+
+impl Walk<'_> {
+    fn dir(&mut self, dir: &Path, prefix: &str) -> Result<(), String> {
+        if !prefix.is_empty()
+            && let Ok(content) = fs::read_to_string(dir.join(".gitignore"))
+        {
+            for layers in [&mut self.current, &mut self.new] {
+                layers.push(Layer {
+                    prefix: prefix.to_string(),
+                    matcher: Matcher::parse(&content),
+                });
+            }
+        }
+
+        for (name, is_dir) in entries(dir)? {
+            let path = format!("{prefix}{name}");
+            if decide(&self.current, &path, is_dir).is_some_and(|p| !p.negated) {
+                continue;
+            }
+
+            match decide(&self.new, &path, is_dir) {
+                Some(pattern) if !pattern.negated => {
+                    if is_dir {
+                        let files = count_files(&dir.join(&name));
+                        self.preview.ignored.push(Hit {
+                            path: format!("{path}/"),
+                            line: pattern.line,
+                            pattern: pattern.text.clone(),
+                            files,
+                        });
+                    } else if !self.tracked.contains(&path) {
+                        self.preview.ignored.push(Hit {
+                            path,
+                            line: pattern.line,
+                            pattern: pattern.text.clone(),
+                            files: 0,
+                        });
+                    }
+                }
+                _ if is_dir => self.dir(&dir.join(&name), &format!("{path}/"))?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn root_layer(content: &str) -> Layer {
+    Layer {
+        prefix: String::new(),
+        matcher: Matcher::parse(content),
+    }
+}
+
+/// The deciding pattern for `path` itself: the last match in the deepest
+/// .gitignore that has one.
+fn decide<'a>(layers: &'a [Layer], path: &str, is_dir: bool) -> Option<&'a Pattern> {
+    let mut applicable: Vec<&Layer> = layers
+        .iter()
+        .filter(|l| path.starts_with(&l.prefix))
+        .collect();
+    applicable.sort_by_key(|l| std::cmp::Reverse(l.prefix.len()));
+    applicable
+        .into_iter()
+        .find_map(|l| l.matcher.last_match(&path[l.prefix.len()..], is_dir))
+}
+
+/// The pattern ignoring `path` or one of its leading directories, if any.
+fn is_ignored<'a>(layers: &'a [Layer], path: &str, is_dir: bool) -> Option<&'a Pattern> {
+    for (i, _) in path.match_indices('/') {
+        if let Some(pattern) = decide(layers, &path[..i], true)
+            && !pattern.negated
+        {
+            return Some(pattern);
+        }
+    }
+    decide(layers, path, is_dir).filter(|p| !p.negated)
+}
+
+/// Directory entries sorted by name, without `.git`.
+fn entries(dir: &Path) -> Result<Vec<(String, bool)>, String> {
+    let mut entries: Vec<(String, bool)> = fs::read_dir(dir)
+        .map_err(|e| format!("failed to read {}: {e}", dir.display()))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let is_dir = entry.file_type().ok()?.is_dir();
+            (name != ".git").then_some((name, is_dir))
+        })
+        .collect();
+    entries.sort_unstable();
+    Ok(entries)
+}
+
+fn count_files(dir: &Path) -> usize {
+    entries(dir).map_or(0, |entries| {
+        entries
+            .into_iter()
+            .map(|(name, is_dir)| {
+                if is_dir {
+                    count_files(&dir.join(name))
+                } else {
+                    1
+                }
+            })
+            .sum()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn unique_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gig_preview_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn paths(hits: &[Hit]) -> Vec<&str> {
+        hits.iter().map(|h| h.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_preview_reports_newly_ignored() {
+        let root = unique_dir("new");
+        write(&root, ".gitignore", "*.log\n");
+        write(&root, "debug.log", "");
+        write(&root, "app.py", "");
+        write(&root, "cache.pyc", "");
+        write(&root, "build/a.o", "");
+        write(&root, "build/b.o", "");
+
+        let result = preview(&root, "*.log\n*.pyc\nbuild/\n", &HashSet::new()).unwrap();
+        assert_eq!(paths(&result.ignored), vec!["build/", "cache.pyc"]);
+        assert_eq!(result.ignored[0].files, 2);
+        assert_eq!(result.ignored[0].line, 3);
+        assert_eq!(result.ignored[1].pattern, "*.pyc");
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_preview_nested_gitignore_takes_precedence() {
+        let root = unique_dir("nested");
+        write(&root, "docs/.gitignore", "!*.pdf\n");
+        write(&root, "docs/guide.pdf", "");
+        write(&root, "report.pdf", "");
+
+        let result = preview(&root, "*.pdf\n", &HashSet::new()).unwrap();
+        assert_eq!(paths(&result.ignored), vec!["report.pdf"]);
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_preview_separates_tracked_files() {
+        let root = unique_dir("tracked");
+        write(&root, ".vscode/settings.json", "");
+        write(&root, "Cargo.lock", "");
+        write(&root, "notes.lock", "");
+        let tracked: HashSet<String> = ["Cargo.lock", ".vscode/settings.json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let result = preview(&root, "*.lock\n.vscode/\n", &tracked).unwrap();
+        assert_eq!(paths(&result.ignored), vec![".vscode/", "notes.lock"]);
+        assert_eq!(
+            paths(&result.tracked),
+            vec![".vscode/settings.json", "Cargo.lock"]
+        );
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_tracked_newly_ignored() {
+        let tracked: Vec<String> = [
+            "Cargo.lock",
+            "src/main.rs",
+            ".vscode/settings.json",
+            "a.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let hits = tracked_newly_ignored("*.log\n", "*.log\n*.lock\n.vscode/\n", &tracked);
+        assert_eq!(paths(&hits), vec!["Cargo.lock", ".vscode/settings.json"]);
+        assert_eq!(hits[1].line, 3);
+    }
+}
@@ -0,0 +1,140 @@
+//! `gig search`: find templates by name, scope or the patterns they contain.
+
+/// A template matching a search term.
+#[derive(Debug, PartialEq)]
+pub struct SearchMatch<'a> {
+    pub key: &'a str,
+    pub scope: String,
+    /// Pattern lines containing the term, with 1-based line numbers.
+    pub lines: Vec<(usize, &'a str)>,
+}
+
+/// Find templates whose key, scope or patterns contain `term` (case-insensitive).
+///
+/// Results are sorted by key.
+pub fn search<'a>(
+    term: &str,
+    templates: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Vec<SearchMatch<'a>> {
+    let term = term.to_lowercase();
+
+    let mut matches: Vec<SearchMatch> = templates
+        .into_iter()
+        .filter_map(|(key, content)| {
+            let scope = scope_label(key);
+            let lines: Vec<(usize, &str)> = content
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| {
+                    let trimmed = line.trim();
+                    !trimmed.is_empty()
+                        && !trimmed.starts_with('#')
+                        && trimmed.to_lowercase().contains(&term)
+                })
+                .map(|(i, line)| (i + 1, line.trim()))
+                .collect();
+
+            let named = key.contains(&term) || scope.contains(&term);
+            (named || !lines.is_empty()).then_some(SearchMatch { key, scope, lines })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| a.key.cmp(b.key));
+    matches
+}
+
+/// Split a template key into its scope (`top-level`, `global` or `community`)
+/// and, for community templates, its subcategory.
+pub fn template_scope(key: &str) -> (&'static str, Option<&str>) {
+    let parts: Vec<&str> = key.splitn(3, '.').collect();
+    match parts.as_slice() {
+        ["global", _, ..] => ("global", None),
+        ["community", sub, _] => ("community", Some(*sub)),
+        ["community", _] => ("community", None),
+        _ => ("top-level", None),
+    }
+}
+
+/// Human-readable scope of a template key, e.g. `global` or `community/golang`.
+pub fn scope_label(key: &str) -> String {
+    match template_scope(key) {
+        (scope, Some(sub)) => format!("{scope}/{sub}"),
+        (scope, None) => scope.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES: &[(&str, &str)] = &[
+        (
+            "terraform",
+            "# Local .terraform directories\n.terraform/\n*.tfstate\n",
+        ),
+        ("python", "__pycache__/\n*.py[cod]\n"),
+        ("community.golang.hugo", "/public/\nhugo.exe\n"),
+        ("global.macos", ".DS_Store\n"),
+    ];
+
+    #[test]
+    fn test_search_matches_patterns_not_comments() {
+        let result = search(".terraform", TEMPLATES.iter().copied());
+        assert_eq!(
+            result,
+            vec![SearchMatch {
+                key: "terraform",
+                scope: "top-level".to_string(),
+                lines: vec![(2, ".terraform/")],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_search_matches_key() {
+        let result = search("hugo", TEMPLATES.iter().copied());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].key, "community.golang.hugo");
+        assert_eq!(result[0].lines, vec![(2, "hugo.exe")]);
+    }
+
+    #[test]
+    fn test_search_matches_scope() {
+        let result = search("golang", TEMPLATES.iter().copied());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].scope, "community/golang");
+
+        let result = search("global", TEMPLATES.iter().copied());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].key, "global.macos");
+    }
+
+    #[test]
+    fn test_search_case_insensitive_and_sorted() {
+        let result = search("DS_STORE", TEMPLATES.iter().copied());
+        assert_eq!(result[0].lines, vec![(1, ".DS_Store")]);
+
+        let result = search("p", TEMPLATES.iter().copied());
+        let keys: Vec<&str> = result.iter().map(|m| m.key).collect();
+        let mut sorted = keys.clone();
+        sorted.sort_unstable();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn test_search_no_match() {
+        assert!(search("nothing-like-this", TEMPLATES.iter().copied()).is_empty());
+    }
+
+    #[test]
+    fn test_template_scope() {
+        assert_eq!(template_scope("python"), ("top-level", None));
+        assert_eq!(template_scope("global.macos"), ("global", None));
+        assert_eq!(
+            template_scope("community.golang.hugo"),
+            ("community", Some("golang"))
+        );
+        assert_eq!(template_scope("community.terraform"), ("community", None));
+        assert_eq!(scope_label("community.golang.hugo"), "community/golang");
+    }
+}
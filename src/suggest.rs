@@ -0,0 +1,129 @@
+//! "Did you mean" suggestions for template names that don't resolve.
+
+/// Rank `keys` by how likely they are what the user meant by `input`, best first.
+///
+/// A key matches when its last dotted component equals `input`, when it or
+/// its last component starts with or contains `input`, or when either is within
+/// a small edit distance of it. At most `limit` keys are returned.
+pub fn suggest<'a>(
+    input: &str,
+    keys: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<&'a str> {
+    let input = input.to_lowercase();
+    let max_distance = input.chars().count().div_ceil(3).max(1);
+
+    let mut ranked: Vec<(u8, usize, &str)> = keys
+        .into_iter()
+        .filter_map(|key| {
+            let last = key.rsplit('.').next().unwrap_or(key);
+            let distance = edit_distance(&input, key).min(edit_distance(&input, last));
+
+            let tier = if last == input {
+                0
+            } else if key.starts_with(&input) || last.starts_with(&input) {
+                1
+            } else if input.len() >= 3 && key.contains(&input) {
+                2
+            } else if distance <= max_distance {
+                3
+            } else {
+                return None;
+            };
+            Some((tier, distance, key))
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| (a.0, a.1, a.2.len(), a.2).cmp(&(b.0, b.1, b.2.len(), b.2)));
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, key)| key)
+        .collect()
+}
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions,
+/// so "pyhton" is one edit away from "python".
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Three rolling rows: i - 2, i - 1 and i
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                curr[j] = curr[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &[&str] = &[
+        "python",
+        "go",
+        "godot",
+        "node",
+        "community.javascript.vue",
+        "community.golang.hugo",
+        "global.macos",
+    ];
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("python", "python"), 0);
+        assert_eq!(edit_distance("pyhton", "python"), 1);
+        assert_eq!(edit_distance("pythn", "python"), 1);
+        assert_eq!(edit_distance("", "go"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_suggest_transposition() {
+        assert_eq!(suggest("pyhton", KEYS.iter().copied(), 3), vec!["python"]);
+    }
+
+    #[test]
+    fn test_suggest_nested_last_component() {
+        let result = suggest("vue", KEYS.iter().copied(), 3);
+        assert_eq!(result.first(), Some(&"community.javascript.vue"));
+    }
+
+    #[test]
+    fn test_suggest_prefix_ranks_before_typos() {
+        let result = suggest("god", KEYS.iter().copied(), 5);
+        assert_eq!(result.first(), Some(&"godot"));
+        assert!(result.contains(&"go"));
+    }
+
+    #[test]
+    fn test_suggest_substring() {
+        let result = suggest("macos", KEYS.iter().copied(), 3);
+        assert_eq!(result, vec!["global.macos"]);
+    }
+
+    #[test]
+    fn test_suggest_respects_limit() {
+        let result = suggest("o", KEYS.iter().copied(), 2);
+        assert!(result.len() <= 2);
+    }
+
+    #[test]
+    fn test_suggest_nothing_close() {
+        assert!(suggest("zzzzzzzz", KEYS.iter().copied(), 3).is_empty());
+    }
+}
@@ -0,0 +1,432 @@
+//! A parser for the subset of TOML that gig's own files use: tables, arrays of
+//! tables, and key/value pairs holding strings, integers, booleans or arrays.
+//! Dotted keys, inline tables, floats, dates and multi-line strings are not
+//! supported and are reported as errors.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The value as a list of strings, if it is an array containing only strings.
+    pub fn as_string_array(&self) -> Option<Vec<String>> {
+        match self {
+            Value::Array(items) => items.iter().map(|v| v.as_str().map(String::from)).collect(),
+            _ => None,
+        }
+    }
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+/// A parsed TOML document.
+#[derive(Debug, Default, PartialEq)]
+pub struct Document {
+    /// Keys before the first table header.
+    pub root: Table,
+    /// `[name]` tables.
+    pub tables: BTreeMap<String, Table>,
+    /// `[[name]]` arrays of tables, in file order.
+    pub array_tables: BTreeMap<String, Vec<Table>>,
+}
+
+/// Parse a TOML document. Errors carry the 1-based line number.
+pub fn parse(input: &str) -> Result<Document, String> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser
+        .document()
+        .map_err(|msg| format!("line {}: {msg}", parser.line))
+}
+
+/// Quote a string as a TOML basic string.
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+enum Target {
+    Root,
+    Table(String),
+    ArrayTable(String),
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn document(&mut self) -> Result<Document, String> {
+        let mut doc = Document::default();
+        let mut target = Target::Root;
+
+        loop {
+            self.skip_blank_lines();
+            let Some(c) = self.peek() else {
+                return Ok(doc);
+            };
+
+            if c == '[' {
+                target = self.header(&mut doc)?;
+            } else {
+                let key = self.key()?;
+                self.skip_spaces();
+                self.expect('=')?;
+                self.skip_spaces();
+                let value = self.value()?;
+                let table = match &target {
+                    Target::Root => &mut doc.root,
+                    Target::Table(name) => doc.tables.get_mut(name).expect("table was created"),
+                    Target::ArrayTable(name) => doc
+                        .array_tables
+                        .get_mut(name)
+                        .and_then(|tables| tables.last_mut())
+                        .expect("array table was created"),
+                };
+                if table.insert(key.clone(), value).is_some() {
+                    return Err(format!("duplicate key \"{key}\""));
+                }
+            }
+            self.end_of_line()?;
+        }
+    }
+
+    fn header(&mut self, doc: &mut Document) -> Result<Target, String> {
+        self.expect('[')?;
+        let is_array = self.peek() == Some('[');
+        if is_array {
+            self.pos += 1;
+        }
+        self.skip_spaces();
+        let name = self.key()?;
+        self.skip_spaces();
+        self.expect(']')?;
+        if is_array {
+            self.expect(']')?;
+            doc.array_tables
+                .entry(name.clone())
+                .or_default()
+                .push(Table::new());
+            Ok(Target::ArrayTable(name))
+        } else {
+            if doc.tables.insert(name.clone(), Table::new()).is_some() {
+                return Err(format!("duplicate table [{name}]"));
+            }
+            Ok(Target::Table(name))
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        let key = match self.peek() {
+            Some('"') => self.basic_string()?,
+            Some('\'') => self.literal_string()?,
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return Err("expected a key".to_string());
+                }
+                self.chars[start..self.pos].iter().collect()
+            }
+        };
+        if self.peek() == Some('.') {
+            return Err("dotted keys are not supported".to_string());
+        }
+        Ok(key)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('t') | Some('f') => {
+                let word = self.word();
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => Err(format!("invalid value \"{word}\"")),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => {
+                let word = self.word();
+                word.replace('_', "")
+                    .parse()
+                    .map(Value::Integer)
+                    .map_err(|_| format!("invalid integer \"{word}\""))
+            }
+            Some('\n') | None => Err("expected a value".to_string()),
+            Some(c) => Err(format!("unexpected character '{c}'")),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank_lines();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                Some(c) => return Err(format!("expected ',' or ']' in array, found '{c}'")),
+                None => return Err("unterminated array".to_string()),
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        if self.peek() == Some('"') && self.chars.get(self.pos + 1) == Some(&'"') {
+            return Err("multi-line strings are not supported".to_string());
+        }
+        let mut out = String::new();
+        loop {
+            let c = match self.peek() {
+                Some('\n') | None => return Err("unterminated string".to_string()),
+                Some(_) => self.next().expect("peeked a character"),
+            };
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escaped = self.next().ok_or("unterminated string")?;
+                    match escaped {
+                        '"' => out.push('"'),
+                        '\\' => out.push('\\'),
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        'u' => {
+                            let hex: String = (0..4).filter_map(|_| self.next()).collect();
+                            let c = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| format!("invalid unicode escape \"\\u{hex}\""))?;
+                            out.push(c);
+                        }
+                        other => return Err(format!("invalid escape \"\\{other}\"")),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                Some('\n') | None => return Err("unterminated string".to_string()),
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    out.push(c);
+                }
+            }
+        }
+    }
+
+    /// Consume a run of characters that can appear in a bare value.
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// After a key/value pair or header: optional spaces and comment, then a
+    /// newline or end of input.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.skip_comment();
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            Some(c) => Err(format!("unexpected character '{c}' after value")),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skip whitespace, comments and newlines.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            if self.peek() == Some('\n') {
+                self.next();
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected '{expected}', found '{c}'")),
+            None => Err(format!("expected '{expected}', found end of file")),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_root_and_tables() {
+        let doc = parse(
+            "name = \"gig\" # trailing comment\n\n[presets]\nteam = [\"rust\", 'global.macos']\n\n[aliases]\njs = \"node\"\n",
+        )
+        .unwrap();
+        assert_eq!(doc.root["name"], Value::String("gig".into()));
+        assert_eq!(
+            doc.tables["presets"]["team"].as_string_array(),
+            Some(vec!["rust".to_string(), "global.macos".to_string()])
+        );
+        assert_eq!(doc.tables["aliases"]["js"].as_str(), Some("node"));
+    }
+
+    #[test]
+    fn test_parse_multiline_array_with_comments() {
+        let doc = parse("list = [\n  \"a\", # first\n  \"b\",\n]\n").unwrap();
+        assert_eq!(
+            doc.root["list"].as_string_array(),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_array_tables() {
+        let doc = parse("[[file]]\npath = \"a\"\n[[file]]\npath = \"b\"\n").unwrap();
+        let files = &doc.array_tables["file"];
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1]["path"].as_str(), Some("b"));
+    }
+
+    #[test]
+    fn test_parse_scalars() {
+        let doc = parse("a = 42\nb = -1_000\nc = true\nd = false\n\"quoted key\" = 'x'\n").unwrap();
+        assert_eq!(doc.root["a"], Value::Integer(42));
+        assert_eq!(doc.root["b"], Value::Integer(-1000));
+        assert_eq!(doc.root["c"], Value::Boolean(true));
+        assert_eq!(doc.root["d"], Value::Boolean(false));
+        assert_eq!(doc.root["quoted key"].as_str(), Some("x"));
+    }
+
+    #[test]
+    fn test_parse_escapes() {
+        let doc = parse(r#"s = "a\"b\\c\n\u00e9""#).unwrap();
+        assert_eq!(doc.root["s"].as_str(), Some("a\"b\\c\né"));
+    }
+
+    #[test]
+    fn test_parse_errors_carry_line_numbers() {
+        let err = parse("a = 1\nb = \"unterminated\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "got: {err}");
+
+        let err = parse("a = 1\na = 2\n").unwrap_err();
+        assert!(err.contains("duplicate key"), "got: {err}");
+
+        let err = parse("[t]\n[t]\n").unwrap_err();
+        assert!(err.contains("duplicate table"), "got: {err}");
+
+        let err = parse("a.b = 1\n").unwrap_err();
+        assert!(err.contains("dotted keys"), "got: {err}");
+
+        let err = parse("a = 1 2\n").unwrap_err();
+        assert!(err.contains("after value"), "got: {err}");
+    }
+
+    #[test]
+    fn test_quote_round_trip() {
+        let original = "tab\there \"quoted\" back\\slash";
+        let doc = parse(&format!("s = {}\n", quote(original))).unwrap();
+        assert_eq!(doc.root["s"].as_str(), Some(original));
+    }
+}
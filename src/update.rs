@@ -0,0 +1,462 @@
+//! `gig update`: fetch a newer template set into a local cache.
+//!
+//! The cache holds a tree laid out like github/gitignore under `templates/`,
+//! plus `source.toml` recording where and when it was fetched. When the cache
+//! exists, the runtime index uses it instead of the embedded templates.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{layout, toml};
+
+/// Where templates are fetched from when no source is configured.
+pub const DEFAULT_SOURCE: &str = "https://github.com/github/gitignore.git";
+
+const TEMPLATES_DIR: &str = "templates";
+const INFO_FILE: &str = "source.toml";
+const TARBALL_SUFFIXES: &[&str] = &[".tar.gz", ".tgz", ".tar"];
+
+/// Where and when the cached template set was fetched.
+#[derive(Debug, PartialEq)]
+pub struct CacheInfo {
+    /// The git URL, tarball or directory it was fetched from.
+    pub source: String,
+    /// Upstream commit, when the source records one.
+    pub commit: Option<String>,
+    /// RFC 3339 UTC timestamp.
+    pub fetched_at: String,
+}
+
+impl CacheInfo {
+    /// Read `source.toml` from `cache`, or None if there is none.
+    pub fn load(cache: &Path) -> Result<Option<CacheInfo>, String> {
+        let path = cache.join(INFO_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+        let doc = toml::parse(&content).map_err(|e| format!("{}: {e}", path.display()))?;
+        let field = |name: &str| doc.root.get(name).and_then(|v| v.as_str());
+
+        Ok(Some(CacheInfo {
+            source: field("source").unwrap_or_default().to_string(),
+            commit: field("commit").map(str::to_string),
+            fetched_at: field("fetched_at").unwrap_or_default().to_string(),
+        }))
+    }
+
+    /// The source and abbreviated commit, e.g. `https://... at 0123abcd4567`.
+    pub fn describe(&self) -> String {
+        match &self.commit {
+            Some(commit) => format!("{} at {}", self.source, short_commit(commit)),
+            None => self.source.clone(),
+        }
+    }
+
+    fn to_toml(&self) -> String {
+        let mut out = format!("source = {}\n", toml::quote(&self.source));
+        if let Some(commit) = &self.commit {
+            out.push_str(&format!("commit = {}\n", toml::quote(commit)));
+        }
+        out.push_str(&format!("fetched_at = {}\n", toml::quote(&self.fetched_at)));
+        out
+    }
+}
+
+/// The first 12 characters of a commit hash.
+pub fn short_commit(commit: &str) -> &str {
+    commit.get(..12).unwrap_or(commit)
+}
+
+/// `$XDG_CACHE_HOME/gig`, falling back to `~/.cache/gig`.
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("gig"))
+}
+
+/// The cached template tree inside `cache`.
+pub fn templates_dir(cache: &Path) -> PathBuf {
+    cache.join(TEMPLATES_DIR)
+}
+
+/// Fetch templates from `source` into `cache`, replacing any previous set.
+///
+/// `source` is a git URL or path, a `.tar.gz`/`.tgz`/`.tar` URL or path, or a
+/// plain directory of templates; `file://` URLs name local paths. The old set
+/// is only replaced once the new one has been fetched and contains templates.
+/// Returns the recorded info and the number of templates.
+pub fn update(source: &str, cache: &Path) -> Result<(CacheInfo, usize), String> {
+    fs::create_dir_all(cache).map_err(|e| format!("failed to create {}: {e}", cache.display()))?;
+
+    let staging = cache.join(format!(".staging-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)
+        .map_err(|e| format!("failed to create {}: {e}", staging.display()))?;
+
+    let result = fetch_and_install(source, cache, &staging);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn fetch_and_install(
+    source: &str,
+    cache: &Path,
+    staging: &Path,
+) -> Result<(CacheInfo, usize), String> {
+    let fetched = staging.join(TEMPLATES_DIR);
+    let local = local_path(source);
+
+    let commit = if is_tarball(source) {
+        fetch_tarball(source, local.as_deref(), staging, &fetched)?
+    } else if let Some(dir) = local.as_deref().filter(|d| d.is_dir() && !is_git_repo(d)) {
+        copy_templates(dir, &fetched)?;
+        None
+    } else {
+        fetch_git(source, local.as_deref(), &fetched)?
+    };
+
+    let mut files = Vec::new();
+    layout::collect_templates(&fetched, &mut files)
+        .map_err(|e| format!("failed to read fetched templates: {e}"))?;
+    if files.is_empty() {
+        return Err(format!("no .gitignore templates found in {source}"));
+    }
+
+    let dest = templates_dir(cache);
+    if dest.exists() {
+        fs::remove_dir_all(&dest)
+            .map_err(|e| format!("failed to remove {}: {e}", dest.display()))?;
+    }
+    fs::rename(&fetched, &dest)
+        .map_err(|e| format!("failed to move templates into {}: {e}", dest.display()))?;
+
+    let info = CacheInfo {
+        source: source.to_string(),
+        commit,
+        fetched_at: format_timestamp(SystemTime::now()),
+    };
+    let info_path = cache.join(INFO_FILE);
+    fs::write(&info_path, info.to_toml())
+        .map_err(|e| format!("failed to write {}: {e}", info_path.display()))?;
+
+    Ok((info, files.len()))
+}
+
+/// The local path a source names, if it isn't a remote URL.
+fn local_path(source: &str) -> Option<PathBuf> {
+    if let Some(path) = source.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    }
+    (!source.contains("://")).then(|| PathBuf::from(source))
+}
+
+fn is_tarball(source: &str) -> bool {
+    TARBALL_SUFFIXES.iter().any(|s| source.ends_with(s))
+}
+
+/// Whether `dir` is a git work tree or bare repository.
+fn is_git_repo(dir: &Path) -> bool {
+    dir.join(".git").exists() || (dir.join("HEAD").is_file() && dir.join("objects").is_dir())
+}
+
+/// Shallow-clone a git repository and return its HEAD commit.
+fn fetch_git(source: &str, local: Option<&Path>, dest: &Path) -> Result<Option<String>, String> {
+    // Local clones ignore --depth unless they go through file://
+    let url = match local {
+        Some(path) => {
+            let path = path
+                .canonicalize()
+                .map_err(|e| format!("cannot read template source {}: {e}", path.display()))?;
+            format!("file://{}", path.display())
+        }
+        None => source.to_string(),
+    };
+
+    run(Command::new("git")
+        .args(["clone", "--quiet", "--depth=1", &url])
+        .arg(dest))?;
+    let head = run(Command::new("git")
+        .arg("-C")
+        .arg(dest)
+        .args(["rev-parse", "HEAD"]))?;
+
+    let git_dir = dest.join(".git");
+    fs::remove_dir_all(&git_dir)
+        .map_err(|e| format!("failed to remove {}: {e}", git_dir.display()))?;
+    Ok(Some(head.trim().to_string()))
+}
+
+/// Download (if remote) and unpack a tarball, returning the commit recorded
+/// by `git archive`, if any.
+fn fetch_tarball(
+    source: &str,
+    local: Option<&Path>,
+    staging: &Path,
+    dest: &Path,
+) -> Result<Option<String>, String> {
+    let archive = match local {
+        Some(path) => path.to_path_buf(),
+        None => {
+            // Keep the suffix so the archive's compression is still known
+            let suffix = TARBALL_SUFFIXES.iter().find(|s| source.ends_with(*s));
+            let file = staging.join(format!("download{}", suffix.unwrap_or(&".tar.gz")));
+            run(Command::new("curl")
+                .args(["--fail", "--silent", "--show-error", "--location", "-o"])
+                .arg(&file)
+                .arg(source))?;
+            file
+        }
+    };
+
+    let unpacked = staging.join("unpacked");
+    fs::create_dir_all(&unpacked)
+        .map_err(|e| format!("failed to create {}: {e}", unpacked.display()))?;
+    run(Command::new("tar")
+        .arg("-xf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&unpacked))?;
+
+    // Archives usually wrap everything in one directory, e.g. gitignore-main/
+    let entries: Vec<PathBuf> = fs::read_dir(&unpacked)
+        .map_err(|e| format!("failed to read {}: {e}", unpacked.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect();
+    let root = match entries.as_slice() {
+        [only] if only.is_dir() => only.clone(),
+        _ => unpacked,
+    };
+    fs::rename(&root, dest).map_err(|e| format!("failed to move {}: {e}", root.display()))?;
+
+    Ok(tar_commit_id(&archive))
+}
+
+/// Read the commit id `git archive` stores in a tarball's header.
+fn tar_commit_id(archive: &Path) -> Option<String> {
+    let gzipped = !archive.to_string_lossy().ends_with(".tar");
+    let mut git = Command::new("git")
+        .arg("get-tar-commit-id")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdin = git.stdin.take()?;
+
+    // get-tar-commit-id stops reading after the header, so feeding it the
+    // rest of the archive may fail with a broken pipe; that's expected
+    if gzipped {
+        let _ = Command::new("gzip")
+            .arg("-dc")
+            .arg(archive)
+            .stdout(stdin)
+            .stderr(Stdio::null())
+            .status();
+    } else {
+        let _ = fs::File::open(archive).and_then(|mut file| std::io::copy(&mut file, &mut stdin));
+        drop(stdin);
+    }
+
+    let output = git.wait_with_output().ok()?;
+    let id = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !id.is_empty()).then_some(id)
+}
+
+/// Copy the templates in a plain directory, keeping their relative paths.
+fn copy_templates(src: &Path, dest: &Path) -> Result<(), String> {
+    fs::create_dir_all(dest).map_err(|e| format!("failed to create {}: {e}", dest.display()))?;
+    let mut files = Vec::new();
+    layout::collect_templates(src, &mut files)
+        .map_err(|e| format!("failed to read templates in {}: {e}", src.display()))?;
+
+    for (path, _) in files {
+        let rel = path
+            .strip_prefix(src)
+            .expect("template should be under src");
+        let target = dest.join(rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+        fs::copy(&path, &target).map_err(|e| format!("failed to copy {}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Run a command, returning its stdout or an error with its stderr.
+fn run(command: &mut Command) -> Result<String, String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run {program}: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{program} failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Format a time as an RFC 3339 UTC timestamp, e.g. `2024-05-01T12:30:00Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn unique_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gig_update_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A small tree laid out like github/gitignore.
+    fn write_upstream(dir: &Path) {
+        fs::create_dir_all(dir.join("Global")).unwrap();
+        fs::write(dir.join("Rust.gitignore"), "/target/\n").unwrap();
+        fs::write(dir.join("Global/macOS.gitignore"), ".DS_Store\n").unwrap();
+        fs::write(dir.join("README.md"), "templates\n").unwrap();
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        run(Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=gig", "-c", "user.email=gig@example.com"])
+            .args(args))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_update_from_plain_directory() {
+        let root = unique_dir("plain");
+        let upstream = root.join("upstream");
+        write_upstream(&upstream);
+        let cache = root.join("cache");
+
+        let (info, count) = update(upstream.to_str().unwrap(), &cache).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(info.commit, None);
+        assert!(
+            templates_dir(&cache)
+                .join("Global/macOS.gitignore")
+                .is_file()
+        );
+        assert!(!templates_dir(&cache).join("README.md").exists());
+        assert_eq!(CacheInfo::load(&cache).unwrap(), Some(info));
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_update_from_git_repository() {
+        let root = unique_dir("git");
+        let upstream = root.join("upstream");
+        write_upstream(&upstream);
+        git(&upstream, &["init", "--quiet"]);
+        git(&upstream, &["add", "."]);
+        git(&upstream, &["commit", "--quiet", "-m", "templates"]);
+        let head = git(&upstream, &["rev-parse", "HEAD"]);
+        let cache = root.join("cache");
+
+        let source = format!("file://{}", upstream.display());
+        let (info, count) = update(&source, &cache).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(info.commit.as_deref(), Some(head.trim()));
+        assert!(!templates_dir(&cache).join(".git").exists());
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_update_from_git_archive_tarball() {
+        let root = unique_dir("tarball");
+        let upstream = root.join("upstream");
+        write_upstream(&upstream);
+        git(&upstream, &["init", "--quiet"]);
+        git(&upstream, &["add", "."]);
+        git(&upstream, &["commit", "--quiet", "-m", "templates"]);
+        let head = git(&upstream, &["rev-parse", "HEAD"]);
+        let archive = root.join("gitignore.tar.gz");
+        git(
+            &upstream,
+            &[
+                "archive",
+                "--prefix=gitignore-main/",
+                "-o",
+                archive.to_str().unwrap(),
+                "HEAD",
+            ],
+        );
+        let cache = root.join("cache");
+
+        let (info, count) = update(archive.to_str().unwrap(), &cache).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(info.commit.as_deref(), Some(head.trim()));
+        assert!(templates_dir(&cache).join("Rust.gitignore").is_file());
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_update_keeps_old_set_when_source_has_no_templates() {
+        let root = unique_dir("empty");
+        let upstream = root.join("upstream");
+        write_upstream(&upstream);
+        let cache = root.join("cache");
+        update(upstream.to_str().unwrap(), &cache).unwrap();
+
+        let empty = root.join("empty");
+        fs::create_dir_all(&empty).unwrap();
+        let err = update(empty.to_str().unwrap(), &cache).unwrap_err();
+        assert!(err.contains("no .gitignore templates"), "got: {err}");
+        assert!(templates_dir(&cache).join("Rust.gitignore").is_file());
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_cache_info_missing() {
+        assert_eq!(
+            CacheInfo::load(Path::new("/nonexistent/gig")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        assert_eq!(format_timestamp(leap_day), "2000-02-29T12:34:56Z");
+    }
+}